@@ -0,0 +1,792 @@
+use std::collections::HashMap;
+
+use crate::cli::{Mode, TransposeCycle};
+
+/// Note names for each pitch class, spelled with sharps.
+const SHARP_NAMES: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+
+/// Note names for each pitch class, spelled with flats.
+const FLAT_NAMES: [&str; 12] = [
+    "C", "Db", "D", "Eb", "E", "F", "Gb", "G", "Ab", "A", "Bb", "B",
+];
+
+/// Key names for each pitch class, spelled the way major keys are usually written.
+const MAJOR_KEY_NAMES: [&str; 12] = [
+    "C", "Db", "D", "Eb", "E", "F", "Gb", "G", "Ab", "A", "Bb", "B",
+];
+
+/// Key names for each pitch class, spelled the way minor keys are usually written.
+const MINOR_KEY_NAMES: [&str; 12] = [
+    "C", "C#", "D", "Eb", "E", "F", "F#", "G", "G#", "A", "Bb", "B",
+];
+
+/// Lowest note that can be played, as a MIDI note number (C2).
+const LOWEST_NOTE: i32 = 36;
+
+/// Highest note that can be played, as a MIDI note number (B5).
+const HIGHEST_NOTE: i32 = 83;
+
+/// Octave of the root when voicing chords, unless another register is chosen.
+pub const DEFAULT_REGISTER: u8 = 3;
+
+/// Chord qualities and the semitones above the root they are voiced with.
+///
+/// Chords are limited to four notes, so extended chords leave out the fifth and altered dominants
+/// leave out the root, the way players usually voice them.
+const QUALITIES: [(&str, &[i32]); 38] = [
+    ("", &[0, 4, 7]),
+    ("maj", &[0, 4, 7]),
+    ("M", &[0, 4, 7]),
+    ("m", &[0, 3, 7]),
+    ("min", &[0, 3, 7]),
+    ("-", &[0, 3, 7]),
+    ("dim", &[0, 3, 6]),
+    ("o", &[0, 3, 6]),
+    ("aug", &[0, 4, 8]),
+    ("+", &[0, 4, 8]),
+    ("sus2", &[0, 2, 7]),
+    ("sus4", &[0, 5, 7]),
+    ("sus", &[0, 5, 7]),
+    ("6", &[0, 4, 7, 9]),
+    ("m6", &[0, 3, 7, 9]),
+    ("7", &[0, 4, 7, 10]),
+    ("maj7", &[0, 4, 7, 11]),
+    ("M7", &[0, 4, 7, 11]),
+    ("m7", &[0, 3, 7, 10]),
+    ("min7", &[0, 3, 7, 10]),
+    ("-7", &[0, 3, 7, 10]),
+    ("mMaj7", &[0, 3, 7, 11]),
+    ("m7b5", &[0, 3, 6, 10]),
+    ("dim7", &[0, 3, 6, 9]),
+    ("o7", &[0, 3, 6, 9]),
+    ("7sus4", &[0, 5, 7, 10]),
+    ("9", &[0, 4, 10, 14]),
+    ("maj9", &[0, 4, 11, 14]),
+    ("m9", &[0, 3, 10, 14]),
+    ("11", &[0, 5, 10, 14]),
+    ("m11", &[0, 3, 10, 17]),
+    ("13", &[0, 4, 10, 21]),
+    ("maj13", &[0, 4, 11, 21]),
+    ("m13", &[0, 3, 10, 21]),
+    ("7b9", &[0, 4, 10, 13]),
+    ("7#9", &[0, 4, 10, 15]),
+    ("7#11", &[0, 4, 10, 18]),
+    ("7alt", &[4, 10, 15, 20]),
+];
+
+/// Upper-case roman numerals for each scale degree, longest first so `IV` is not read as `I`.
+const NUMERALS: [(&str, usize); 7] = [
+    ("VII", 6),
+    ("III", 2),
+    ("VI", 5),
+    ("IV", 3),
+    ("II", 1),
+    ("V", 4),
+    ("I", 0),
+];
+
+/// Semitones of each degree of the major scale above its tonic.
+const MAJOR_SCALE: [i32; 7] = [0, 2, 4, 5, 7, 9, 11];
+
+/// Pitch classes of the major keys written with flats, for keys named without an accidental.
+const FLAT_MAJOR_KEYS: [i32; 4] = [5, 10, 3, 8];
+
+/// A key that roman numerals and Nashville numbers are resolved in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Key {
+    /// Semitones of the tonic above C.
+    tonic: i32,
+    /// Semitones of each scale degree above the tonic.
+    scale: [i32; 7],
+    /// Whether chords in the key are spelled with flats rather than sharps.
+    flats: bool,
+    mode: Mode,
+}
+
+impl Key {
+    /// Parses a key from a tonic such as `Eb` or `F#` and a mode.
+    pub fn parse(tonic: &str, mode: Mode) -> Result<Self, String> {
+        let invalid = || {
+            format!(
+                "Invalid value for key: {}. Expected a note A-G with an optional # or b (i.e. Eb).",
+                tonic
+            )
+        };
+        let name = tonic.trim();
+        let ((tonic, _), rest) = parse_root(name).ok_or_else(invalid)?;
+        if !rest.is_empty() {
+            return Err(invalid());
+        }
+
+        let degree = mode_degree(mode);
+        let scale = std::array::from_fn(|i| {
+            (MAJOR_SCALE[(degree + i) % 7] - MAJOR_SCALE[degree]).rem_euclid(12)
+        });
+
+        Ok(Key {
+            tonic,
+            scale,
+            flats: key_uses_flats(name, tonic, mode),
+            mode,
+        })
+    }
+
+    /// The key moved by the given number of semitones, spelled the way that key is usually written.
+    pub fn transposed(&self, semitones: i32) -> Key {
+        if semitones.rem_euclid(12) == 0 {
+            return *self;
+        }
+        let tonic = (self.tonic + semitones).rem_euclid(12);
+        let names = match self.mode {
+            Mode::Major | Mode::Lydian | Mode::Mixolydian => MAJOR_KEY_NAMES,
+            _ => MINOR_KEY_NAMES,
+        };
+        Key {
+            tonic,
+            flats: key_uses_flats(names[tonic as usize], tonic, self.mode),
+            ..*self
+        }
+    }
+
+    /// Whether chords in the key are spelled with flats rather than sharps.
+    pub fn flats(&self) -> bool {
+        self.flats
+    }
+
+    /// Names the key, such as `Eb` or `F# dorian`.
+    pub fn name(&self) -> String {
+        let names = if self.flats { FLAT_NAMES } else { SHARP_NAMES };
+        let mode = match self.mode {
+            Mode::Major => "",
+            Mode::Lydian => " lydian",
+            Mode::Mixolydian => " mixolydian",
+            Mode::Dorian => " dorian",
+            Mode::Phrygian => " phrygian",
+            Mode::Minor => " minor",
+            Mode::Locrian => " locrian",
+        };
+        format!("{}{}", names[self.tonic.rem_euclid(12) as usize], mode)
+    }
+}
+
+/// Whether a key is spelled with flats. Keys named with an accidental keep it. Others are spelled like
+/// their relative major, so D dorian uses sharps like C major and C minor uses flats like Eb major.
+fn key_uses_flats(name: &str, tonic: i32, mode: Mode) -> bool {
+    let relative_major = (tonic - MAJOR_SCALE[mode_degree(mode)]).rem_euclid(12);
+    if name.len() > 1 {
+        name.ends_with('b')
+    } else {
+        FLAT_MAJOR_KEYS.contains(&relative_major)
+    }
+}
+
+/// A chord as its root and the semitones of each note above the root.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Chord {
+    /// Semitones of the root above C, from -1 for Cb to 12 for B#.
+    pub root: i32,
+    /// Semitones above the root, from lowest to highest.
+    pub intervals: &'static [i32],
+    /// Whether the chord is spelled with flats rather than sharps.
+    pub flats: bool,
+}
+
+impl Chord {
+    /// Voices the chord with its root in the given octave, moving it an octave at a time
+    /// if any note would be out of range.
+    ///
+    /// # Returns
+    ///
+    /// The note names from lowest to highest, such as `["C3", "E3", "G3", "B3"]`.
+    pub fn voice(&self, register: u8) -> Vec<String> {
+        let mut root = (register as i32 + 1) * 12 + self.root;
+        if root < LOWEST_NOTE {
+            root += 12;
+        }
+        let top = self.intervals.iter().max().copied().unwrap_or(0);
+        while root + top > HIGHEST_NOTE && root - 12 >= LOWEST_NOTE {
+            root -= 12;
+        }
+
+        self.intervals
+            .iter()
+            .map(|interval| note_name(root + interval, self.flats))
+            .collect()
+    }
+}
+
+/// Parses a chord symbol such as `Cmaj7`, `F#m7b5` or `Bb13`.
+///
+/// # Returns
+///
+/// * `Ok(Chord)` - The root and intervals of the chord.
+/// * `Err(String)` - An error naming the part of the symbol that was not understood.
+pub fn parse_symbol(symbol: &str) -> Result<Chord, String> {
+    let (root, quality) = parse_root(symbol).ok_or_else(|| {
+        format!(
+            "Invalid chord symbol {}: expected a root note A-G with an optional # or b.",
+            symbol
+        )
+    })?;
+
+    let intervals = quality_intervals(quality).ok_or_else(|| {
+        format!(
+            "Unknown chord symbol {}: the quality '{}' is not supported. Supported qualities are: {}.",
+            symbol,
+            quality,
+            supported_qualities()
+        )
+    })?;
+
+    Ok(Chord {
+        root: root.0,
+        intervals,
+        flats: root.1,
+    })
+}
+
+/// Parses a roman numeral such as `ii7` or `bVII`, or a Nashville number such as `4` or `6m7`, in the given key.
+///
+/// Numerals count degrees of the key's mode, so `III` is Eb in C minor. A `b` or `#` in front lowers or
+/// raises the degree of the parallel major instead, so `bVII` is G in A minor and F in G mixolydian.
+/// Upper-case roman numerals are major and lower-case ones minor, with any quality after them.
+/// Nashville numbers are major unless marked minor, as in `6m`, whatever the mode.
+///
+/// # Returns
+///
+/// * `Ok(Chord)` - The root and intervals of the chord.
+/// * `Err(String)` - An error naming the part of the numeral that was not understood.
+pub fn parse_numeral(numeral: &str, key: &Key) -> Result<Chord, String> {
+    let invalid = || {
+        format!(
+            "Invalid numeral {}: expected a roman numeral (i.e. ii7) or a Nashville number (i.e. 6m7), optionally after b or #.",
+            numeral
+        )
+    };
+
+    let (shift, rest) = match numeral.chars().next() {
+        Some('b') => (Some(-1), &numeral[1..]),
+        Some('#') => (Some(1), &numeral[1..]),
+        _ => (None, numeral),
+    };
+    let (degree, quality) = match rest.chars().next() {
+        Some(digit @ '1'..='7') => (digit as usize - '1' as usize, rest[1..].to_string()),
+        _ => {
+            let upper = NUMERALS.iter().find(|(name, _)| rest.starts_with(name));
+            let lower = NUMERALS
+                .iter()
+                .find(|(name, _)| rest.starts_with(&name.to_lowercase()));
+            match (upper, lower) {
+                (Some((name, degree)), _) => (*degree, rest[name.len()..].to_string()),
+                (None, Some((name, degree))) => (*degree, minor_quality(&rest[name.len()..])),
+                (None, None) => return Err(invalid()),
+            }
+        }
+    };
+
+    let intervals = quality_intervals(&quality).ok_or_else(|| {
+        format!(
+            "Unknown numeral {}: the quality '{}' is not supported. Supported qualities are: {}.",
+            numeral,
+            quality,
+            supported_qualities()
+        )
+    })?;
+
+    // Accidentals alter the major scale, so a b or # never moves a note the mode has already altered.
+    let interval = match shift {
+        Some(shift) => MAJOR_SCALE[degree] + shift,
+        None => key.scale[degree],
+    };
+    Ok(Chord {
+        root: (key.tonic + interval).rem_euclid(12),
+        intervals,
+        flats: key.flats,
+    })
+}
+
+/// Voices each chord symbol in the given register, keyed by the symbol.
+///
+/// # Arguments
+///
+/// * `symbols` - Chord symbols such as `Dm7` or `G7`.
+/// * `register` - The octave of each root, from 2 to 5.
+pub fn symbol_tone_map(
+    symbols: &[String],
+    register: u8,
+) -> Result<HashMap<String, Vec<String>>, String> {
+    symbols
+        .iter()
+        .map(|symbol| Ok((symbol.clone(), parse_symbol(symbol)?.voice(register))))
+        .collect()
+}
+
+/// Voices each roman numeral or Nashville number in the given key and register, keyed by the numeral.
+///
+/// # Arguments
+///
+/// * `numerals` - Numerals such as `ii7` or `5`.
+/// * `key` - The key the numerals are counted in.
+/// * `register` - The octave of each root, from 2 to 5.
+pub fn numeral_tone_map(
+    numerals: &[String],
+    key: &Key,
+    register: u8,
+) -> Result<HashMap<String, Vec<String>>, String> {
+    numerals
+        .iter()
+        .map(|numeral| {
+            Ok((
+                numeral.clone(),
+                parse_numeral(numeral, key)?.voice(register),
+            ))
+        })
+        .collect()
+}
+
+/// The semitones each pass of a transpose cycle is moved from the written chords, starting as written.
+///
+/// Each pass is moved by at most a tritone either way, so the chords stay near the register they were
+/// written in. A random cycle lists the keys chromatically, and the order they play in is shuffled as
+/// the metronome runs.
+pub fn cycle_offsets(cycle: TransposeCycle) -> Vec<i32> {
+    let offsets: Vec<i32> = match cycle {
+        TransposeCycle::Fourths => (0..12).map(|pass| pass * 5).collect(),
+        TransposeCycle::Fifths => (0..12).map(|pass| pass * 7).collect(),
+        TransposeCycle::Chromatic | TransposeCycle::Random => (0..12).collect(),
+    };
+    offsets
+        .into_iter()
+        .map(|offset| (offset + 6).rem_euclid(12) - 6)
+        .collect()
+}
+
+/// Transposes notes by the given number of semitones, moving them together by octaves if any
+/// would leave the supported range. Notes that can't be read are left out.
+///
+/// # Arguments
+///
+/// * `notes` - Note names such as `C3` or `Eb4`.
+/// * `semitones` - How far to move the notes, up or down.
+/// * `flats` - Whether to spell the moved notes with flats, following the key they are moved to.
+pub fn transpose(notes: &[String], semitones: i32, flats: bool) -> Vec<String> {
+    let numbers: Vec<i32> = notes
+        .iter()
+        .filter_map(|note| note_number(note))
+        .map(|number| number + semitones)
+        .collect();
+    fit_range(&numbers)
+        .iter()
+        .map(|&number| note_name(number, flats))
+        .collect()
+}
+
+/// Moves MIDI note numbers together by octaves until none is above B5 or below C2.
+pub fn fit_range(numbers: &[i32]) -> Vec<i32> {
+    let (Some(&low), Some(&high)) = (numbers.iter().min(), numbers.iter().max()) else {
+        return vec![];
+    };
+    let shift = if high > HIGHEST_NOTE {
+        -12 * ((high - HIGHEST_NOTE + 11) / 12)
+    } else if low < LOWEST_NOTE {
+        12 * ((LOWEST_NOTE - low + 11) / 12)
+    } else {
+        0
+    };
+    numbers.iter().map(|number| number + shift).collect()
+}
+
+/// Whether a chord is written with flats, so notes worked out from it can be spelled the same way.
+pub fn spelled_with_flats(notes: &[String]) -> bool {
+    notes.iter().any(|note| note.get(1..2) == Some("b"))
+}
+
+/// Voices a chord as close as possible to the chord before it, choosing the inversion and octave
+/// that move the voices least in total. The chord keeps its number of notes and stays within the
+/// supported range. Notes that can't be read are left out.
+///
+/// # Arguments
+///
+/// * `previous` - The notes of the chord played before, as voiced.
+/// * `notes` - The notes of the chord to voice.
+pub fn voice_lead(previous: &[String], notes: &[String]) -> Vec<String> {
+    let previous: Vec<i32> = previous
+        .iter()
+        .filter_map(|note| note_number(note))
+        .collect();
+    let written: Vec<i32> = notes.iter().filter_map(|note| note_number(note)).collect();
+    if previous.is_empty() || written.is_empty() {
+        return notes.to_vec();
+    }
+
+    // Ties go to the voicing nearest where the chord was written, so the progression doesn't drift.
+    let Some(voicing) = close_voicings(&written)
+        .into_iter()
+        .min_by_key(|voicing| (movement(&previous, voicing), movement(&written, voicing)))
+    else {
+        return notes.to_vec();
+    };
+    let flats = spelled_with_flats(notes);
+    voicing
+        .iter()
+        .map(|&number| note_name(number, flats))
+        .collect()
+}
+
+/// Every close voicing of the notes within the supported range: each inversion, stacked upward from
+/// each octave it fits in. Doubled notes stay an octave apart.
+fn close_voicings(notes: &[i32]) -> Vec<Vec<i32>> {
+    let mut voicings = Vec::new();
+    for inversion in 0..notes.len() {
+        let mut stacked: Vec<i32> = Vec::with_capacity(notes.len());
+        for &note in notes[inversion..].iter().chain(&notes[..inversion]) {
+            stacked.push(match stacked.last() {
+                Some(&below) => below + (note - below - 1).rem_euclid(12) + 1,
+                None => LOWEST_NOTE + note.rem_euclid(12),
+            });
+        }
+        while stacked.last().is_some_and(|&top| top <= HIGHEST_NOTE) {
+            voicings.push(stacked.clone());
+            stacked.iter_mut().for_each(|note| *note += 12);
+        }
+    }
+    voicings
+}
+
+/// Total semitones the voices move from one chord to the next. Chords with the same number of notes
+/// move voice by voice from the bottom up. Otherwise each new note is measured from the nearest old one.
+fn movement(from: &[i32], to: &[i32]) -> i32 {
+    let mut from = from.to_vec();
+    from.sort_unstable();
+    let mut to = to.to_vec();
+    to.sort_unstable();
+    if from.len() == to.len() {
+        from.iter().zip(&to).map(|(a, b)| (a - b).abs()).sum()
+    } else {
+        to.iter()
+            .map(|b| from.iter().map(|a| (a - b).abs()).min().unwrap_or(0))
+            .sum()
+    }
+}
+
+/// The MIDI note number of a note name, such as 60 for C4.
+pub fn note_number(note: &str) -> Option<i32> {
+    let ((semitones, _), octave) = parse_root(note)?;
+    Some((octave.parse::<i32>().ok()? + 1) * 12 + semitones)
+}
+
+/// The semitones of a chord quality such as `m7`, if it is supported.
+fn quality_intervals(quality: &str) -> Option<&'static [i32]> {
+    QUALITIES
+        .iter()
+        .find(|(name, _)| *name == quality)
+        .map(|(_, intervals)| *intervals)
+}
+
+/// Lists the supported chord qualities for error messages.
+fn supported_qualities() -> String {
+    QUALITIES
+        .iter()
+        .filter(|(name, _)| !name.is_empty())
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The chord quality of a lower-case roman numeral, which is minor unless it says otherwise.
+fn minor_quality(quality: &str) -> String {
+    match quality {
+        "o" | "dim" | "o7" | "dim7" | "m7b5" => quality.to_string(),
+        "ø" | "ø7" => "m7b5".to_string(),
+        "maj7" => "mMaj7".to_string(),
+        _ => format!("m{}", quality),
+    }
+}
+
+/// The degree of the major scale each mode starts on.
+fn mode_degree(mode: Mode) -> usize {
+    match mode {
+        Mode::Major => 0,
+        Mode::Dorian => 1,
+        Mode::Phrygian => 2,
+        Mode::Lydian => 3,
+        Mode::Mixolydian => 4,
+        Mode::Minor => 5,
+        Mode::Locrian => 6,
+    }
+}
+
+/// Splits the root off a chord symbol.
+///
+/// # Returns
+///
+/// The semitones of the root above C, whether to spell the chord with flats, and the rest of the symbol.
+fn parse_root(symbol: &str) -> Option<((i32, bool), &str)> {
+    let mut chars = symbol.chars();
+    let natural = match chars.next()? {
+        'C' => 0,
+        'D' => 2,
+        'E' => 4,
+        'F' => 5,
+        'G' => 7,
+        'A' => 9,
+        'B' => 11,
+        _ => return None,
+    };
+    let rest = chars.as_str();
+    Some(match rest.chars().next() {
+        Some('#') => ((natural + 1, false), &rest[1..]),
+        Some('b') => ((natural - 1, true), &rest[1..]),
+        // F is the only natural key signature with a flat.
+        _ => ((natural, natural == 5), rest),
+    })
+}
+
+/// The root of a chord symbol or note, such as Bb for Bbm7 or Bb3.
+pub fn root_name(symbol: &str) -> Option<&str> {
+    let (_, rest) = parse_root(symbol)?;
+    Some(&symbol[..symbol.len() - rest.len()])
+}
+
+/// Names a MIDI note number, such as 60 for C4.
+pub fn note_name(note: i32, flats: bool) -> String {
+    let names = if flats { FLAT_NAMES } else { SHARP_NAMES };
+    format!("{}{}", names[note.rem_euclid(12) as usize], note / 12 - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("C", 3, &["C3", "E3", "G3"])]
+    #[case("Cmaj7", 3, &["C3", "E3", "G3", "B3"])]
+    #[case("F#m7b5", 3, &["F#3", "A3", "C4", "E4"])]
+    #[case("G7alt", 3, &["B3", "F4", "A#4", "D#5"])]
+    #[case("Bb13", 3, &["Bb3", "D4", "Ab4", "G5"])]
+    #[case("Dm7", 2, &["D2", "F2", "A2", "C3"])]
+    #[case("Ebmaj7", 3, &["Eb3", "G3", "Bb3", "D4"])]
+    #[case("Fsus4", 3, &["F3", "Bb3", "C4"])]
+    fn parse_symbol_voices_chords(
+        #[case] symbol: &str,
+        #[case] register: u8,
+        #[case] expected: &[&str],
+    ) {
+        assert_eq!(parse_symbol(symbol).unwrap().voice(register), expected);
+    }
+
+    #[rstest]
+    fn voice_moves_down_to_fit_the_range() {
+        assert_eq!(
+            parse_symbol("B13").unwrap().voice(5),
+            vec!["B3", "D#4", "A4", "G#5"]
+        );
+    }
+
+    #[rstest]
+    #[case("Cmaj7", 2)]
+    #[case("Bb13", 5)]
+    #[case("G7alt", 4)]
+    #[case("Cbm9", 3)]
+    #[case("Cb", 2)]
+    #[case("B#13", 5)]
+    fn voiced_notes_have_frequencies(#[case] symbol: &str, #[case] register: u8) {
+        for note in parse_symbol(symbol).unwrap().voice(register) {
+            assert!(helpers::note_to_frequency(&note).is_some(), "{}", note);
+        }
+    }
+
+    fn numerals(key: &str, mode: Mode, numerals: &[&str]) -> Vec<Vec<String>> {
+        let key = Key::parse(key, mode).unwrap();
+        numerals
+            .iter()
+            .map(|numeral| parse_numeral(numeral, &key).unwrap().voice(3))
+            .collect()
+    }
+
+    #[rstest]
+    fn roman_numerals_resolve_in_the_key() {
+        assert_eq!(
+            numerals("Eb", Mode::Major, &["ii7", "V7", "Imaj7", "vi7"]),
+            vec![
+                vec!["F3", "Ab3", "C4", "Eb4"],
+                vec!["Bb3", "D4", "F4", "Ab4"],
+                vec!["Eb3", "G3", "Bb3", "D4"],
+                vec!["C3", "Eb3", "G3", "Bb3"],
+            ]
+        );
+    }
+
+    #[rstest]
+    fn nashville_numbers_resolve_in_the_key() {
+        assert_eq!(
+            numerals("G", Mode::Major, &["1", "4", "5", "6m"]),
+            vec![
+                vec!["G3", "B3", "D4"],
+                vec!["C3", "E3", "G3"],
+                vec!["D3", "F#3", "A3"],
+                vec!["E3", "G3", "B3"],
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case("C", Mode::Minor, "III", &["Eb3", "G3", "Bb3"])]
+    #[case("A", Mode::Minor, "iv", &["D3", "F3", "A3"])]
+    #[case("A", Mode::Minor, "bVII", &["G3", "B3", "D4"])]
+    #[case("A", Mode::Minor, "bIII", &["C3", "E3", "G3"])]
+    #[case("A", Mode::Minor, "1", &["A3", "C#4", "E4"])]
+    #[case("A", Mode::Minor, "4m", &["D3", "F3", "A3"])]
+    #[case("D", Mode::Dorian, "IV", &["G3", "B3", "D4"])]
+    #[case("E", Mode::Phrygian, "II", &["F3", "A3", "C4"])]
+    #[case("F", Mode::Lydian, "II", &["G3", "B3", "D4"])]
+    #[case("G", Mode::Mixolydian, "bVII", &["F3", "A3", "C4"])]
+    #[case("B", Mode::Locrian, "i", &["B3", "D4", "F#4"])]
+    #[case("C", Mode::Major, "viiø7", &["B3", "D4", "F4", "A4"])]
+    #[case("C", Mode::Major, "viio7", &["B3", "D4", "F4", "G#4"])]
+    #[case("C", Mode::Major, "#iv", &["F#3", "A3", "C#4"])]
+    fn numerals_follow_the_mode(
+        #[case] key: &str,
+        #[case] mode: Mode,
+        #[case] numeral: &str,
+        #[case] expected: &[&str],
+    ) {
+        assert_eq!(numerals(key, mode, &[numeral])[0], expected);
+    }
+
+    #[rstest]
+    #[case("8", "Invalid numeral 8")]
+    #[case("Dm7", "Invalid numeral Dm7")]
+    #[case("VIII", "Unknown numeral VIII: the quality 'I' is not supported")]
+    #[case(
+        "ii7b5b5",
+        "Unknown numeral ii7b5b5: the quality 'm7b5b5' is not supported"
+    )]
+    fn parse_numeral_explains_errors(#[case] numeral: &str, #[case] message: &str) {
+        let key = Key::parse("C", Mode::Major).unwrap();
+        let err = parse_numeral(numeral, &key).unwrap_err();
+        assert!(err.starts_with(message), "{}", err);
+    }
+
+    #[rstest]
+    #[case("C", Mode::Major, 5, "F", true)]
+    #[case("C", Mode::Major, -2, "Bb", true)]
+    #[case("C", Mode::Major, 6, "Gb", true)]
+    #[case("C", Mode::Major, 2, "D", false)]
+    #[case("A", Mode::Minor, 4, "C# minor", false)]
+    #[case("A", Mode::Minor, -3, "F# minor", false)]
+    #[case("A", Mode::Minor, 5, "D minor", true)]
+    #[case("D", Mode::Dorian, 0, "D dorian", false)]
+    #[case("C#", Mode::Major, 0, "C#", false)]
+    fn transposed_key_is_named_and_spelled_for_the_new_key(
+        #[case] tonic: &str,
+        #[case] mode: Mode,
+        #[case] semitones: i32,
+        #[case] name: &str,
+        #[case] flats: bool,
+    ) {
+        let key = Key::parse(tonic, mode).unwrap().transposed(semitones);
+        assert_eq!(key.name(), name);
+        assert_eq!(key.flats(), flats);
+    }
+
+    #[rstest]
+    #[case(TransposeCycle::Fourths, vec![0, 5, -2, 3, -4, 1, -6, -1, 4, -3, 2, -5])]
+    #[case(TransposeCycle::Fifths, vec![0, -5, 2, -3, 4, -1, -6, 1, -4, 3, -2, 5])]
+    #[case(TransposeCycle::Chromatic, vec![0, 1, 2, 3, 4, 5, -6, -5, -4, -3, -2, -1])]
+    #[case(TransposeCycle::Random, vec![0, 1, 2, 3, 4, 5, -6, -5, -4, -3, -2, -1])]
+    fn cycle_offsets_visit_every_key(#[case] cycle: TransposeCycle, #[case] expected: Vec<i32>) {
+        assert_eq!(cycle_offsets(cycle), expected);
+    }
+
+    #[rstest]
+    #[case(&["D3", "F3", "A3", "C4"], 5, true, &["G3", "Bb3", "D4", "F4"])]
+    #[case(&["C3", "E3", "G3"], -2, true, &["Bb2", "D3", "F3"])]
+    #[case(&["C3", "E3", "G3"], 6, false, &["F#3", "A#3", "C#4"])]
+    #[case(&["G5", "B5"], 2, false, &["A4", "C#5"])]
+    #[case(&["C2", "E2"], -1, true, &["B2", "Eb3"])]
+    #[case(&["Cb3", "E#3"], 0, false, &["B2", "F3"])]
+    fn transpose_stays_in_range(
+        #[case] notes: &[&str],
+        #[case] semitones: i32,
+        #[case] flats: bool,
+        #[case] expected: &[&str],
+    ) {
+        let notes: Vec<String> = notes.iter().map(|note| note.to_string()).collect();
+        assert_eq!(transpose(&notes, semitones, flats), expected);
+    }
+
+    #[rstest]
+    // ii-V-I in root position becomes smooth inversions.
+    #[case(&["D3", "F3", "A3", "C4"], &["G3", "B3", "D4", "F4"], &["D3", "F3", "G3", "B3"])]
+    #[case(&["D3", "F3", "G3", "B3"], &["C4", "E4", "G4", "B4"], &["C3", "E3", "G3", "B3"])]
+    #[case(&["C3", "E3", "G3"], &["F3", "A3", "C4"], &["C3", "F3", "A3"])]
+    // Different note counts are measured from the nearest note.
+    #[case(&["C3", "E3", "G3"], &["G3", "B3", "D4", "F4"], &["B2", "D3", "F3", "G3"])]
+    #[case(&["C4", "Eb4", "G4"], &["Bb3", "D4", "F4"], &["Bb3", "D4", "F4"])]
+    fn voice_lead_moves_voices_least(
+        #[case] previous: &[&str],
+        #[case] notes: &[&str],
+        #[case] expected: &[&str],
+    ) {
+        let previous: Vec<String> = previous.iter().map(|note| note.to_string()).collect();
+        let notes: Vec<String> = notes.iter().map(|note| note.to_string()).collect();
+        assert_eq!(voice_lead(&previous, &notes), expected);
+    }
+
+    #[rstest]
+    fn voice_lead_stays_in_range() {
+        let previous: Vec<String> = ["C2", "E2", "G2"].iter().map(|n| n.to_string()).collect();
+        let notes: Vec<String> = ["A2", "C3", "E3"].iter().map(|n| n.to_string()).collect();
+        let voiced = voice_lead(&previous, &notes);
+        assert_eq!(voiced, ["C2", "E2", "A2"]);
+        assert!(voiced
+            .iter()
+            .all(|note| (LOWEST_NOTE..=HIGHEST_NOTE).contains(&note_number(note).unwrap())));
+    }
+
+    #[rstest]
+    #[case("Eb")]
+    #[case("F#")]
+    fn key_parse_accepts_tonics(#[case] tonic: &str) {
+        assert!(Key::parse(tonic, Mode::Major).is_ok());
+    }
+
+    #[rstest]
+    #[case("H")]
+    #[case("Ebm")]
+    #[case("")]
+    fn key_parse_rejects_other_names(#[case] tonic: &str) {
+        assert!(Key::parse(tonic, Mode::Major).is_err());
+    }
+
+    #[rstest]
+    #[case("Bbm7", Some("Bb"))]
+    #[case("Bb3", Some("Bb"))]
+    #[case("F#", Some("F#"))]
+    #[case("E4", Some("E"))]
+    #[case("verse", None)]
+    fn root_name_splits_the_root_off(#[case] symbol: &str, #[case] expected: Option<&str>) {
+        assert_eq!(root_name(symbol), expected);
+    }
+
+    #[rstest]
+    #[case("H7", "Invalid chord symbol H7: expected a root note")]
+    #[case("", "Invalid chord symbol : expected a root note")]
+    #[case(
+        "Cmaj8",
+        "Unknown chord symbol Cmaj8: the quality 'maj8' is not supported"
+    )]
+    #[case(
+        "F#m7b6",
+        "Unknown chord symbol F#m7b6: the quality 'm7b6' is not supported"
+    )]
+    fn parse_symbol_explains_errors(#[case] symbol: &str, #[case] message: &str) {
+        let err = parse_symbol(symbol).unwrap_err();
+        assert!(err.starts_with(message), "{}", err);
+    }
+}
@@ -1,14 +1,49 @@
-use clap::Parser;
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use crate::constants::DEFAULT_SAMPLE_RATE;
+
+/// Command line interface for the metronome application.
+///
+/// Without a subcommand the metronome plays live using the top-level options.
+#[derive(Parser, Debug)]
+#[command(
+    author,
+    version,
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub options: Option<CliOptions>,
+
+    #[command(flatten)]
+    pub output: OutputOptions,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Render the metronome to a WAV file instead of playing it
+    Render(Box<RenderOptions>),
+    /// List audio hosts, output devices and their supported configurations
+    Devices,
+}
 
 /// CLI options for the metronome application.
-#[derive(Parser, Debug, Clone)]
-#[command(author, version, about, long_about = None)]
+#[derive(Args, Debug, Clone)]
 pub struct CliOptions {
-    /// Beats per minute
+    /// Beats per minute, decimals allowed (i.e. 92.5)
     #[arg(long)]
-    pub bpm: u32,
+    pub bpm: f64,
 
     /// Beat dropping pattern as "on,off" (i.e. 4,8) or a single number used for both on and off.
+    /// Either part can be a range (i.e. 4,2-8) to pick a new length from it every cycle.
     #[arg(long)]
     pub drop_beats: Option<String>,
 
@@ -16,26 +51,116 @@ pub struct CliOptions {
     #[arg(long)]
     pub drop_rate: Option<u8>,
 
+    /// Seed for random beat dropping and drop-beats ranges, to repeat the same pattern of dropped beats
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Clicks per beat, 2-6 (i.e. 2 for eighths, 3 for triplets). A comma separated list switches between them, i.e. 1,2,3
+    #[arg(long)]
+    pub subdivide: Option<String>,
+
+    /// Beats to play each subdivision in a list before switching to the next (default: one bar)
+    #[arg(long)]
+    pub subdivide_every: Option<u8>,
+
+    /// Time signature as "beats/unit" (i.e. 7/8). Accents the first beat of each bar unless accents are given.
+    #[arg(long)]
+    pub meter: Option<String>,
+
+    /// Accented beats of the bar, as beat numbers (i.e. 1,4) or a pattern where > is accented (i.e. >..>...). Requires meter.
+    #[arg(long)]
+    pub accents: Option<String>,
+
+    /// Beat groupings as sizes joined by + (i.e. 2+2+3). The first beat of each group gets a different click
+    #[arg(long)]
+    pub groups: Option<String>,
+
+    /// Rhythm pattern where each character is a step: x = hit, X = accent, g = ghost, . = rest.
+    /// Bars are separated by | and each bar spans one bar of the meter (i.e. "x..x..x.|x.x.x.x.")
+    #[arg(long)]
+    pub pattern: Option<String>,
+
+    /// Polyrhythm as "cross:beats" (i.e. 3:2), playing two click streams panned apart over the same number of beats
+    #[arg(long)]
+    pub poly: Option<String>,
+
+    /// Swing for subdivisions, as the percentage of each pair of steps given to the first (50-75, i.e. 60) or a ratio (i.e. 2:1)
+    #[arg(long)]
+    pub swing: Option<String>,
+
+    /// Straighten the swing as the tempo rises, from the full swing at 120 BPM to straight at 300 BPM
+    #[arg(long)]
+    pub swing_curve: bool,
+
     /// BPM ramp target
     #[arg(long)]
-    pub ramp: Option<u32>,
+    pub ramp: Option<f64>,
 
-    /// BPM change rate (for ramping)
+    /// BPM change rate (for ramping), decimals allowed (i.e. 0.5), or percent per second for an exponential ramp
     #[arg(long)]
-    pub change_rate: Option<u8>,
+    pub change_rate: Option<f64>,
+
+    /// How the ramp moves between bpm and its target (default bounce)
+    #[arg(long, value_enum)]
+    pub ramp_shape: Option<RampShape>,
+
+    /// BPM change for each step of the speed trainer, toward until (i.e. 5)
+    #[arg(long)]
+    pub step: Option<f64>,
+
+    /// Beats or bars to play at each tempo before stepping or modulating (i.e. 8 or 2bars)
+    #[arg(long)]
+    pub every: Option<String>,
+
+    /// Tempo where the speed trainer stops stepping
+    #[arg(long)]
+    pub until: Option<f64>,
+
+    /// What the speed trainer does on reaching until (default hold)
+    #[arg(long, value_enum)]
+    pub at_end: Option<StepEnd>,
+
+    /// Metric modulation as "old:new" beats taking the same time (i.e. 3:2 turns a dotted quarter into the new quarter).
+    /// Switches between the two tempos at bar boundaries, every given number of beats or bars
+    #[arg(long)]
+    pub modulate: Option<String>,
 
     /// Drone tones (comma separated)
     #[arg(long)]
     pub drone: Option<String>,
 
-    /// Tones for harmonic click
+    /// Tones for harmonic click, as notes (i.e. C3,E3,G3), chords with notes (i.e. Cmaj(C3 E3 G3)) or chord symbols (i.e. Cmaj7,G7)
     #[arg(long)]
     pub tones: Option<String>,
 
-    /// Chord progression for harmonic click
+    /// Chord progression for harmonic click, as chord IDs from tones or chord symbols (i.e. Dm7,G7,Cmaj7)
     #[arg(long)]
     pub progression: Option<String>,
 
+    /// Octave of the root when voicing chord symbols, 2-5 (default 3)
+    #[arg(long)]
+    pub register: Option<u8>,
+
+    /// Key for a progression of roman numerals (i.e. ii7,V7,Imaj7) or Nashville numbers (i.e. 1,4,5)
+    #[arg(long)]
+    pub key: Option<String>,
+
+    /// Mode of the key (default major)
+    #[arg(long, value_enum)]
+    pub mode: Option<Mode>,
+
+    /// Transpose the chord progression after each pass, moving through all 12 keys
+    #[arg(long, value_enum)]
+    pub transpose_cycle: Option<TransposeCycle>,
+
+    /// Choose inversions and octaves for each chord of the progression so the voices move as little as possible
+    #[arg(long)]
+    pub voice_lead: bool,
+
+    /// Voicing for every chord of the progression, or a comma-separated voicing for each chord
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub voicing: Option<Vec<Voicing>>,
+
     /// Beats per chord in progression
     #[arg(long)]
     pub beats_per: Option<String>,
@@ -45,7 +170,144 @@ pub struct CliOptions {
     pub harmonic: bool,
 }
 
-impl CliOptions {
+/// CLI options for rendering the metronome to a file.
+#[derive(Args, Debug, Clone)]
+pub struct RenderOptions {
+    #[command(flatten)]
+    pub metronome: CliOptions,
+
+    /// Length of the recording, as seconds or with units (i.e. 90, 90s, 2m, 1m30s)
+    #[arg(long)]
+    pub duration: String,
+
+    /// Path of the WAV file to write
+    #[arg(long)]
+    pub out: PathBuf,
+
+    /// Sample rate of the WAV file in Hz
+    #[arg(long, default_value_t = DEFAULT_SAMPLE_RATE)]
+    pub sample_rate: u32,
+}
+
+/// CLI options choosing where live playback is sent.
+#[derive(Args, Debug, Clone)]
+pub struct OutputOptions {
+    /// Where to send the audio while playing
+    #[arg(long, value_enum, default_value_t = OutputKind::Device)]
+    pub output: OutputKind,
+
+    /// Path of the WAV file to record to when output is "file"
+    #[arg(long)]
+    pub out: Option<PathBuf>,
+
+    /// Output device name or index, as shown by the devices subcommand
+    #[arg(long)]
+    pub device: Option<String>,
+
+    /// Audio host name, as shown by the devices subcommand
+    #[arg(long)]
+    pub host: Option<String>,
+
+    /// Sample rate in Hz. Devices use their default rate if not set, or the nearest supported rate
+    #[arg(long)]
+    pub sample_rate: Option<u32>,
+
+    /// Device buffer size in frames. Smaller buffers lower latency but need more CPU
+    #[arg(long)]
+    pub buffer_size: Option<u32>,
+}
+
+/// Destinations for live playback.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputKind {
+    /// The default audio output device
+    Device,
+    /// Discard the audio, for running without a sound card
+    Null,
+    /// Record to a 16-bit PCM WAV file
+    File,
+    /// Write raw signed 16-bit little-endian mono PCM to stdout
+    Stdout,
+}
+
+/// Curves a ramp can follow between the starting and target tempo.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RampShape {
+    /// Move linearly back and forth between the two tempos
+    #[default]
+    Bounce,
+    /// Move linearly to the target once, then keep playing at it
+    Hold,
+    /// Move linearly to the target once, then stop the metronome
+    Stop,
+    /// Ease back and forth between the two tempos along a sine wave
+    Sine,
+    /// Change by a constant percentage per second, back and forth between the two tempos
+    Exponential,
+}
+
+/// Modes that roman numerals and Nashville numbers are counted in.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Mode {
+    #[default]
+    #[value(alias = "ionian")]
+    Major,
+    Dorian,
+    Phrygian,
+    Lydian,
+    Mixolydian,
+    #[value(alias = "aeolian")]
+    Minor,
+    Locrian,
+}
+
+/// Orders of keys a chord progression can be transposed through.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransposeCycle {
+    /// Up a fourth each pass, around the circle of fourths
+    Fourths,
+    /// Up a fifth each pass, around the circle of fifths
+    Fifths,
+    /// Up a semitone each pass
+    Chromatic,
+    /// Every key once in a random order, repeated. Use seed to repeat the order
+    Random,
+}
+
+/// Ways of arranging the notes of a chord, taking its first note as the root.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Voicing {
+    /// Root position, with the other notes stacked closely above the root
+    Root,
+    /// First inversion, with the root moved up an octave
+    #[value(name = "1st")]
+    First,
+    /// Second inversion, with the two lowest notes moved up an octave
+    #[value(name = "2nd")]
+    Second,
+    /// Root position with the second note from the top dropped an octave
+    Drop2,
+    /// Root position with the third note from the top dropped an octave
+    Drop3,
+    /// Only the root, third and seventh (or sixth)
+    Shell,
+    /// The root in the bass and the other notes an octave above
+    Spread,
+}
+
+/// What the speed trainer does once it reaches its target tempo.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StepEnd {
+    /// Keep playing at the target tempo
+    #[default]
+    Hold,
+    /// Jump back to the starting tempo and step toward the target again
+    Reset,
+    /// Step back to the starting tempo, then toward the target again
+    Reverse,
+}
+
+impl Cli {
     pub fn parse() -> Self {
         clap::Parser::parse()
     }
@@ -0,0 +1,112 @@
+/// Fraction of a sample within which a beat counts as due, absorbing floating point error
+/// so beats that land exactly on a sample are not pushed to the next one.
+const TOLERANCE: f64 = 1e-6;
+
+/// Counts down to each beat in fractional samples, so beat times never accumulate rounding error.
+///
+/// Each beat is scheduled exactly one period after the previous one, and plays on the first
+/// sample at or after that time. The leftover fraction carries into the next beat, so the
+/// long-run tempo matches the requested BPM regardless of the sample rate.
+#[derive(Clone, Debug, Default)]
+pub struct BeatClock {
+    /// Samples until the next beat is due, including any fractional part.
+    until_beat: f64,
+}
+
+impl BeatClock {
+    /// Creates a clock with the first beat due on the next sample.
+    pub fn new() -> Self {
+        BeatClock { until_beat: 0.0 }
+    }
+
+    /// Whether a beat is due on the current sample.
+    pub fn beat_due(&self) -> bool {
+        self.until_beat < TOLERANCE
+    }
+
+    /// Schedules the next beat one period after the one that was just due.
+    ///
+    /// # Arguments
+    ///
+    /// * `beat_samples` - The length of the beat that just started, in samples.
+    pub fn schedule(&mut self, beat_samples: f64) {
+        self.until_beat += beat_samples;
+    }
+
+    /// Moves the clock on by one sample.
+    pub fn tick(&mut self) {
+        self.until_beat -= 1.0;
+    }
+}
+
+/// The length of one beat in samples.
+pub fn beat_samples(bpm: f64, sample_rate: f64) -> f64 {
+    60.0 / bpm * sample_rate
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    /// Runs the clock at a fixed tempo and records the sample each beat starts on.
+    fn beat_starts(bpm: f64, sample_rate: f64, beats: usize) -> Vec<u64> {
+        let mut clock = BeatClock::new();
+        let mut starts = Vec::with_capacity(beats);
+        let mut sample = 0;
+        while starts.len() < beats {
+            if clock.beat_due() {
+                starts.push(sample);
+                clock.schedule(beat_samples(bpm, sample_rate));
+            }
+            clock.tick();
+            sample += 1;
+        }
+        starts
+    }
+
+    #[rstest]
+    #[case(120.0, 44100.0)]
+    #[case(113.0, 44100.0)]
+    #[case(92.5, 48000.0)]
+    #[case(300.0, 22050.0)]
+    fn beats_start_on_the_first_sample_at_their_exact_time(
+        #[case] bpm: f64,
+        #[case] sample_rate: f64,
+    ) {
+        let period = beat_samples(bpm, sample_rate);
+        for (n, start) in beat_starts(bpm, sample_rate, 500).into_iter().enumerate() {
+            let exact = n as f64 * period;
+            assert!(
+                (start as f64) > exact - TOLERANCE && (start as f64) < exact + 1.0 - TOLERANCE,
+                "beat {n} started at sample {start}, expected {exact}"
+            );
+        }
+    }
+
+    #[rstest]
+    fn long_run_tempo_does_not_drift() {
+        // 113 BPM has a period of 23415.929... samples, which rounding to whole samples would skew.
+        let sample_rate = 44100.0;
+        let period = beat_samples(113.0, sample_rate);
+        let mut clock = BeatClock::new();
+        let mut sample: u64 = 0;
+        let mut beats: u64 = 0;
+
+        // Run for an hour, skipping straight to each beat rather than ticking through every sample.
+        while sample < 3600 * 44100 {
+            if clock.beat_due() {
+                beats += 1;
+                clock.schedule(period);
+            }
+            let skip = (clock.until_beat - TOLERANCE).ceil() as u64;
+            clock.until_beat -= skip as f64;
+            sample += skip;
+        }
+
+        let expected_until_beat = beats as f64 * period - sample as f64;
+        let drift_seconds = (clock.until_beat - expected_until_beat).abs() / sample_rate;
+        assert_eq!(beats, 113 * 60);
+        assert!(drift_seconds < 1e-6, "drifted by {drift_seconds} seconds");
+    }
+}
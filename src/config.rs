@@ -1,23 +1,73 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+};
 
 use regex::Regex;
 
 use crate::{
-    cli::CliOptions,
-    constants::{CHORD_REGEX, NOTE_REGEX},
-    helpers,
+    chord,
+    cli::{
+        CliOptions, OutputKind, OutputOptions, RampShape, RenderOptions, StepEnd, TransposeCycle,
+        Voicing,
+    },
+    constants::{
+        CHORD_REGEX, DEFAULT_BEATS_PER_BAR, MAX_BPM, MAX_BUFFER_SIZE, MAX_PATTERN_STEPS_PER_BEAT,
+        MAX_SAMPLE_RATE, MAX_SWING, MIN_BPM, MIN_BUFFER_SIZE, MIN_SAMPLE_RATE, MIN_SWING,
+        NOTE_REGEX,
+    },
+    helpers, modulation,
+    pattern::{self, Step},
 };
 
+/// Lowest and highest number of beats in one part of a drop-beats cycle.
+pub type BeatRange = (u8, u8);
+
 #[derive(Clone, Debug)]
 pub struct AppConfig {
-    pub bpm: u32,
-    pub drop_beats: Option<(u8, u8)>,
+    pub bpm: f64,
+    /// Ranges of beats to play and to drop.
+    pub drop_beats: Option<(BeatRange, BeatRange)>,
     pub drop_rate: Option<u8>,
-    pub ramp: Option<u32>,
-    pub change_rate: Option<u8>,
+    pub seed: Option<u64>,
+    pub subdivide: Option<Vec<u8>>,
+    pub subdivide_every: Option<u8>,
+    /// Beats per bar and the note value of each beat.
+    pub meter: Option<(u8, u8)>,
+    /// Whether each beat of the bar is accented. Set whenever a meter is given.
+    pub accents: Option<Vec<bool>>,
+    /// Number of beats in each group of the bar.
+    pub groups: Option<Vec<u8>>,
+    /// Steps of each bar of a rhythm pattern.
+    pub pattern: Option<Vec<Vec<Step>>>,
+    /// Share of each pair of steps given to the first, from 0.5 (straight) to 0.75.
+    pub swing: Option<f64>,
+    pub swing_curve: bool,
+    /// Clicks of the cross stream and beats of the base stream in a polyrhythm.
+    pub poly: Option<(u8, u8)>,
+    pub ramp: Option<f64>,
+    pub change_rate: Option<f64>,
+    pub ramp_shape: RampShape,
+    /// BPM change for each step of the speed trainer.
+    pub step: Option<f64>,
+    /// Beats to play at each tempo before stepping, with bars already counted as beats.
+    pub every: Option<u32>,
+    /// Tempo where the speed trainer stops stepping.
+    pub until: Option<f64>,
+    pub at_end: StepEnd,
+    /// Metric modulation as old beats and the new beats that take the same time.
+    pub modulate: Option<(u8, u8)>,
     pub drone: Option<Vec<String>>,
     pub tones: Option<Tones>,
     pub progression: Option<Vec<String>>,
+    /// Key the progression's numerals were read in, used to name each key of a transpose cycle.
+    pub key: Option<chord::Key>,
+    /// Order of keys to move the progression through after each pass.
+    pub transpose_cycle: Option<TransposeCycle>,
+    /// Whether each chord of the progression is voiced to move as little as possible from the one before.
+    pub voice_lead: bool,
+    /// Voicing of each chord of the progression, or a single voicing for every chord.
+    pub voicing: Option<Vec<Voicing>>,
     pub beats_per: Option<Vec<u8>>,
     pub harmonic: bool, // TODO: I don't think I actually need this because I can use tones.is_some().
 }
@@ -28,60 +78,244 @@ pub enum Tones {
     Map(HashMap<String, Vec<String>>),
 }
 
+/// Configuration for rendering the metronome to a file.
+#[derive(Clone, Debug)]
+pub struct RenderConfig {
+    pub app: AppConfig,
+    /// Length of the recording in seconds.
+    pub duration: f64,
+    pub out: PathBuf,
+    pub sample_rate: u32,
+}
+
+impl RenderConfig {
+    /// Constructs a RenderConfig from the render subcommand options.
+    pub fn from_cli(cli: RenderOptions) -> Result<Self, String> {
+        let duration = helpers::parse_duration(&cli.duration, "duration")?;
+        if duration <= 0.0 {
+            return Err("Invalid value for duration: must be longer than zero.".to_string());
+        }
+        let sample_rate = helpers::validate_and_extract(
+            cli.sample_rate,
+            MIN_SAMPLE_RATE,
+            MAX_SAMPLE_RATE,
+            "sample-rate",
+        )?;
+
+        Ok(RenderConfig {
+            app: AppConfig::from_cli(cli.metronome)?,
+            duration,
+            out: cli.out,
+            sample_rate,
+        })
+    }
+}
+
+/// Configuration for where live playback is sent.
+#[derive(Clone, Debug)]
+pub struct OutputConfig {
+    pub kind: OutputKind,
+    pub out: Option<PathBuf>,
+    pub device: Option<String>,
+    pub host: Option<String>,
+    /// Requested sample rate in Hz. Devices fall back to a supported rate if needed.
+    pub sample_rate: Option<u32>,
+    /// Requested device buffer size in frames.
+    pub buffer_size: Option<u32>,
+}
+
+impl OutputConfig {
+    /// Constructs an OutputConfig from the output CLI options.
+    pub fn from_cli(cli: OutputOptions) -> Result<Self, String> {
+        let sample_rate = helpers::validate_and_extract_option(
+            cli.sample_rate,
+            MIN_SAMPLE_RATE,
+            MAX_SAMPLE_RATE,
+            "sample-rate",
+        )?;
+        let buffer_size = helpers::validate_and_extract_option(
+            cli.buffer_size,
+            MIN_BUFFER_SIZE,
+            MAX_BUFFER_SIZE,
+            "buffer-size",
+        )?;
+
+        match (cli.output, &cli.out) {
+            (OutputKind::File, None) => {
+                return Err("File output requires a path. Please set out.".to_string())
+            }
+            (OutputKind::File, Some(_)) => {}
+            (_, Some(_)) => {
+                eprintln!("Warning: out is set but output is not file. out will be ignored.")
+            }
+            (_, None) => {}
+        }
+
+        if cli.output != OutputKind::Device
+            && (cli.device.is_some() || cli.host.is_some() || buffer_size.is_some())
+        {
+            eprintln!("Warning: device, host or buffer-size is set but output is not device. They will be ignored.");
+        }
+
+        Ok(OutputConfig {
+            kind: cli.output,
+            out: cli.out,
+            device: cli.device,
+            host: cli.host,
+            sample_rate,
+            buffer_size,
+        })
+    }
+}
+
 impl AppConfig {
     /// Constructs an AppConfig from the CLI options.
     pub fn from_cli(cli: CliOptions) -> Result<Self, String> {
         // Validate integer arguments.
-        let bpm = helpers::validate_and_extract(cli.bpm, 30, 300, "bpm")?;
+        let bpm = helpers::validate_and_extract(cli.bpm, MIN_BPM, MAX_BPM, "bpm")?;
         let drop_rate = helpers::validate_and_extract_option(cli.drop_rate, 1, 99, "drop-rate")?;
-        let ramp = helpers::validate_and_extract_option(cli.ramp, 30, 300, "ramp")?;
+        let ramp = helpers::validate_and_extract_option(cli.ramp, MIN_BPM, MAX_BPM, "ramp")?;
         let change_rate =
-            helpers::validate_and_extract_option(cli.change_rate, 1, 99, "change-rate")?;
+            helpers::validate_and_extract_option(cli.change_rate, 0.1, 99.0, "change-rate")?;
+        let step = helpers::validate_and_extract_option(cli.step, 0.1, 100.0, "step")?;
+        let until = helpers::validate_and_extract_option(cli.until, MIN_BPM, MAX_BPM, "until")?;
+        let subdivide_every =
+            helpers::validate_and_extract_option(cli.subdivide_every, 1, 64, "subdivide-every")?;
 
         // Extract list types.
         let drone = Self::get_drone(cli.drone)?;
         let progression = Self::get_progression(cli.progression)?;
-        let beats_per = helpers::parse_comma_separated_option::<u8>(cli.beats_per, "beats-per")?;
+        let beats_per = Self::get_beats_per(cli.beats_per)?;
+        let subdivide = Self::get_subdivide(cli.subdivide)?;
 
         // Extract complex types.
         let drop_beats = Self::get_drop_beats(cli.drop_beats)?;
-        let tones = Self::get_tones(cli.tones)?;
+        let meter = Self::get_meter(cli.meter)?;
+        let poly = Self::get_poly(cli.poly)?;
+        let modulate = Self::get_modulate(cli.modulate)?;
+        let groups = Self::get_groups(cli.groups)?;
+        let swing = Self::get_swing(cli.swing)?;
+        let pattern = Self::get_pattern(cli.pattern, meter)?;
+        // A rhythm pattern has its own accents, so the meter only accents the bar when asked to.
+        let accents = if pattern.is_some() && cli.accents.is_none() {
+            None
+        } else {
+            Self::get_accents(cli.accents, meter)?
+        };
+        let register = helpers::validate_and_extract(
+            cli.register.unwrap_or(chord::DEFAULT_REGISTER),
+            2,
+            5,
+            "register",
+        )?;
+        let key = cli
+            .key
+            .as_deref()
+            .map(|key| chord::Key::parse(key, cli.mode.unwrap_or_default()))
+            .transpose()?;
+        if key.is_some() && cli.tones.is_some() {
+            return Err(
+                "Cannot set both key and tones. The key turns the progression's numerals into chords."
+                    .to_string(),
+            );
+        }
+        if key.is_some() && progression.is_none() {
+            eprintln!("Warning: key is set but progression is not. key will be ignored.");
+        }
+        if cli.mode.is_some() && key.is_none() {
+            eprintln!("Warning: mode is set but key is not. mode will be ignored.");
+        }
+        let tones = match (Self::get_tones(cli.tones, register)?, &progression, key) {
+            // A progression of numerals or chord symbols needs no tones to define its chords.
+            (None, Some(progression), Some(key)) if cli.harmonic => Some(Tones::Map(
+                chord::numeral_tone_map(progression, &key, register)?,
+            )),
+            (None, Some(progression), None) if cli.harmonic => {
+                Some(Tones::Map(chord::symbol_tone_map(progression, register)?))
+            }
+            (tones, _, _) => tones,
+        };
 
-        let config = AppConfig {
+        let mut config = AppConfig {
             bpm,
             drop_beats,
             drop_rate,
+            seed: cli.seed,
+            subdivide,
+            subdivide_every,
+            meter,
+            accents,
+            groups,
+            pattern,
+            swing,
+            swing_curve: cli.swing_curve,
+            poly,
             ramp,
             change_rate,
+            ramp_shape: cli.ramp_shape.unwrap_or_default(),
+            step,
+            every: None,
+            until,
+            at_end: cli.at_end.unwrap_or_default(),
+            modulate,
             drone,
             tones,
             progression,
+            key,
+            transpose_cycle: cli.transpose_cycle,
+            voice_lead: cli.voice_lead,
+            voicing: cli.voicing,
             beats_per,
             harmonic: cli.harmonic,
         };
 
+        // Bars are counted in beats of the meter or groups, which are only known now.
+        config.every = Self::get_every(cli.every, config.beats_per_bar())?;
+
         config.perform_logical_validations()?;
         config.print_warnings();
 
         Ok(config)
     }
 
+    /// The number of beats in a bar, from the meter or the groups if given.
+    pub fn beats_per_bar(&self) -> u8 {
+        match (self.meter, &self.groups) {
+            (Some((beats, _)), _) => beats,
+            (None, Some(groups)) => groups.iter().sum(),
+            (None, None) => DEFAULT_BEATS_PER_BAR,
+        }
+    }
+
     /// Runs all logical validations. Returns an error if any check fails.
     fn perform_logical_validations(&self) -> Result<(), String> {
         self.no_tones_progression_or_beats_per_if_not_harmonic()?;
         self.progression_and_beats_per_set_if_tones_is_map()?;
-        self.no_simultaneous_drop_beats_and_drop_rate()?;
         self.no_drop_beats_or_rate_with_ramp()?;
+        self.step_options_set_together()?;
+        self.no_step_with_ramp()?;
+        self.modulate_options_valid()?;
         self.progression_requires_beats_per()?;
         self.progression_and_beats_per_length_match()?;
         self.progression_and_tones_match()?;
+        self.transpose_cycle_requires_progression()?;
+        self.voicing_options_valid()?;
         self.no_simultaneous_drone_and_tones()?;
+        self.no_pattern_with_beat_options()?;
+        self.no_poly_with_other_rhythms()?;
+        self.groups_fill_the_meter()?;
         Ok(())
     }
 
     /// Prints warnings to stderr (if any).
     fn print_warnings(&self) {
         self.change_rate_warning();
+        self.ramp_shape_warning();
+        self.at_end_warning();
+        self.seed_warning();
+        self.subdivide_every_warning();
+        self.swing_warnings();
+        self.voice_lead_warning();
     }
 
     fn get_progression(progression: Option<String>) -> Result<Option<Vec<String>>, String> {
@@ -123,7 +357,7 @@ impl AppConfig {
     }
 
     /// Gets the tones parameter and returns it as a Tones enum.
-    fn get_tones(tones: Option<String>) -> Result<Option<Tones>, String> {
+    fn get_tones(tones: Option<String>, register: u8) -> Result<Option<Tones>, String> {
         match tones {
             Some(list) => {
                 let chord_re = Regex::new(&format!("^{}", CHORD_REGEX))
@@ -153,7 +387,14 @@ impl AppConfig {
                     }
                     Ok(Some(Tones::List(parsed_list)))
                 } else {
-                    Err(format!("Received an unexpected value for tones: {list}."))
+                    if parsed_list.len() > 10 {
+                        return Err("Chord list must contain between 1 and 10 chords.".to_string());
+                    }
+                    // Anything else must be chord symbols, which are voiced into a map like chords with notes.
+                    Ok(Some(Tones::Map(chord::symbol_tone_map(
+                        &parsed_list,
+                        register,
+                    )?)))
                 }
             }
             None => Ok(None),
@@ -208,20 +449,16 @@ impl AppConfig {
         Ok(chord_map)
     }
 
-    /// Gets the drop_beats parameter.
-    fn get_drop_beats(dropped: Option<String>) -> Result<Option<(u8, u8)>, String> {
+    /// Gets the drop_beats parameter as the lowest and highest number of beats to play and to drop.
+    /// Each part is a number or a range such as "2-8".
+    fn get_drop_beats(dropped: Option<String>) -> Result<Option<(BeatRange, BeatRange)>, String> {
         let param_name = "drop-beats";
         match dropped {
             Some(val) => {
-                let parts: Vec<u8> = helpers::parse_comma_separated(&val, param_name)?;
-                for num in &parts {
-                    if *num > 24 || *num < 1 {
-                        return Err(format!(
-                            "Invalid value for {}: {}. Must be between 1 and 24.",
-                            param_name, num
-                        ));
-                    }
-                }
+                let parts = val
+                    .split(',')
+                    .map(|part| Self::get_drop_beats_range(part.trim(), param_name))
+                    .collect::<Result<Vec<_>, _>>()?;
                 match parts.len() {
                     1 => Ok(Some((parts[0], parts[0]))),
                     2 => Ok(Some((parts[0], parts[1]))),
@@ -235,6 +472,359 @@ impl AppConfig {
         }
     }
 
+    /// Parses one part of drop-beats, either a single number or a "low-high" range.
+    fn get_drop_beats_range(part: &str, param_name: &str) -> Result<BeatRange, String> {
+        let parse = |num: &str| {
+            num.trim()
+                .parse::<u8>()
+                .ok()
+                .filter(|num| (1..=24).contains(num))
+                .ok_or(format!(
+                    "Invalid value for {}: {}. Must be between 1 and 24.",
+                    param_name, num
+                ))
+        };
+        match part.split_once('-') {
+            Some((low, high)) => {
+                let (low, high) = (parse(low)?, parse(high)?);
+                if low > high {
+                    return Err(format!(
+                        "Invalid range for {}: {}. The low end must come first.",
+                        param_name, part
+                    ));
+                }
+                Ok((low, high))
+            }
+            None => {
+                let num = parse(part)?;
+                Ok((num, num))
+            }
+        }
+    }
+
+    /// Gets the every parameter as a number of beats. Given as beats (i.e. 8) or bars (i.e. 2bars).
+    fn get_every(every: Option<String>, beats_per_bar: u8) -> Result<Option<u32>, String> {
+        let param_name = "every";
+        let Some(val) = every else {
+            return Ok(None);
+        };
+        let val = val.trim();
+        let (count, unit_beats) = match val.strip_suffix("bars").or(val.strip_suffix("bar")) {
+            Some(count) => (count, beats_per_bar as u32),
+            None => (val.strip_suffix("beats").unwrap_or(val), 1),
+        };
+        let count = count.trim().parse::<u32>().map_err(|_| {
+            format!(
+                "Invalid value for {}: {}. Expected a number of beats or bars (i.e. 8 or 2bars).",
+                param_name, val
+            )
+        })?;
+        let count = helpers::validate_and_extract(count, 1, 64, param_name)?;
+        Ok(Some(count * unit_beats))
+    }
+
+    /// Gets the meter parameter, given as "beats/unit".
+    fn get_meter(meter: Option<String>) -> Result<Option<(u8, u8)>, String> {
+        let param_name = "meter";
+        let Some(val) = meter else {
+            return Ok(None);
+        };
+
+        let invalid = || {
+            format!(
+                "Invalid value for {}: {}. Expected a time signature like 4/4 or 7/8.",
+                param_name, val
+            )
+        };
+        let (beats, unit) = val.split_once('/').ok_or_else(invalid)?;
+        let beats = beats.trim().parse::<u8>().map_err(|_| invalid())?;
+        let unit = unit.trim().parse::<u8>().map_err(|_| invalid())?;
+
+        helpers::validate_and_extract(beats, 1, 32, param_name)?;
+        if !matches!(unit, 1 | 2 | 4 | 8 | 16 | 32) {
+            return Err(format!(
+                "Invalid value for {}: {}. The note value must be 1, 2, 4, 8, 16 or 32.",
+                param_name, val
+            ));
+        }
+        Ok(Some((beats, unit)))
+    }
+
+    /// Gets the beat groupings, given as sizes joined by + (i.e. 2+2+3).
+    fn get_groups(groups: Option<String>) -> Result<Option<Vec<u8>>, String> {
+        let param_name = "groups";
+        let Some(val) = groups else {
+            return Ok(None);
+        };
+
+        let groups = val
+            .split('+')
+            .map(|size| {
+                let size = size.trim().parse::<u8>().map_err(|err| {
+                    format!(
+                        "Problem parsing value '{}' for {}: {}",
+                        size, param_name, err
+                    )
+                })?;
+                helpers::validate_and_extract(size, 1, 16, param_name)
+            })
+            .collect::<Result<Vec<u8>, String>>()?;
+
+        let total: u32 = groups.iter().map(|&size| size as u32).sum();
+        if total > 32 {
+            return Err(format!(
+                "Invalid value for {}: {}. Groups can add up to at most 32 beats.",
+                param_name, val
+            ));
+        }
+        Ok(Some(groups))
+    }
+
+    /// Gets the swing as the share of each pair of steps given to the first.
+    /// Accepts a percentage (i.e. 60) or a ratio of long to short (i.e. 2:1).
+    fn get_swing(swing: Option<String>) -> Result<Option<f64>, String> {
+        let param_name = "swing";
+        let Some(val) = swing else {
+            return Ok(None);
+        };
+
+        let invalid = || {
+            format!(
+                "Invalid value for {}: {}. Expected a percentage like 60 or a ratio like 2:1.",
+                param_name, val
+            )
+        };
+        let percent = match val.split_once(':') {
+            Some((long, short)) => {
+                let long = long.trim().parse::<f64>().map_err(|_| invalid())?;
+                let short = short.trim().parse::<f64>().map_err(|_| invalid())?;
+                if long <= 0.0 || short <= 0.0 {
+                    return Err(invalid());
+                }
+                100.0 * long / (long + short)
+            }
+            None => val.trim().parse::<f64>().map_err(|_| invalid())?,
+        };
+
+        if !(MIN_SWING..=MAX_SWING).contains(&percent) {
+            return Err(format!(
+                "Invalid value for {}: {} is {:.1}%, outside the range [{}, {}]",
+                param_name, val, percent, MIN_SWING, MAX_SWING
+            ));
+        }
+        Ok(Some(percent / 100.0))
+    }
+
+    /// Gets the polyrhythm, given as "cross:beats".
+    fn get_poly(poly: Option<String>) -> Result<Option<(u8, u8)>, String> {
+        let param_name = "poly";
+        let Some(val) = poly else {
+            return Ok(None);
+        };
+
+        let invalid = || {
+            format!(
+                "Invalid value for {}: {}. Expected a ratio like 3:2 or 7:4.",
+                param_name, val
+            )
+        };
+        let (cross, base) = val.split_once(':').ok_or_else(invalid)?;
+        let cross = cross.trim().parse::<u8>().map_err(|_| invalid())?;
+        let base = base.trim().parse::<u8>().map_err(|_| invalid())?;
+
+        helpers::validate_and_extract(cross, 1, 16, param_name)?;
+        helpers::validate_and_extract(base, 1, 16, param_name)?;
+        Ok(Some((cross, base)))
+    }
+
+    /// Gets the metric modulation, given as "old:new".
+    fn get_modulate(modulate: Option<String>) -> Result<Option<(u8, u8)>, String> {
+        let param_name = "modulate";
+        let Some(val) = modulate else {
+            return Ok(None);
+        };
+
+        let invalid = || {
+            format!(
+                "Invalid value for {}: {}. Expected a ratio like 3:2 or 1:3.",
+                param_name, val
+            )
+        };
+        let (old, new) = val.split_once(':').ok_or_else(invalid)?;
+        let old = old.trim().parse::<u8>().map_err(|_| invalid())?;
+        let new = new.trim().parse::<u8>().map_err(|_| invalid())?;
+
+        helpers::validate_and_extract(old, 1, 16, param_name)?;
+        helpers::validate_and_extract(new, 1, 16, param_name)?;
+        if old == new {
+            return Err(format!(
+                "Invalid value for {}: {}. The ratio must change the tempo.",
+                param_name, val
+            ));
+        }
+        Ok(Some((old, new)))
+    }
+
+    /// Gets the rhythm pattern. Each bar spans one bar of the meter, so its steps must divide evenly into the beats.
+    fn get_pattern(
+        pattern: Option<String>,
+        meter: Option<(u8, u8)>,
+    ) -> Result<Option<Vec<Vec<Step>>>, String> {
+        let Some(val) = pattern else {
+            return Ok(None);
+        };
+
+        let bars = pattern::parse_pattern(&val)?;
+        let beats = meter.map_or(DEFAULT_BEATS_PER_BAR, |(beats, _)| beats) as usize;
+        for (bar, column) in bars.iter().zip(pattern::bar_columns(&val)) {
+            let message = if bar.len() % beats != 0 {
+                format!(
+                    "bar has {} steps, which do not divide evenly into {} beats",
+                    bar.len(),
+                    beats
+                )
+            } else if bar.len() / beats > MAX_PATTERN_STEPS_PER_BEAT {
+                format!(
+                    "bar has {} steps per beat, but at most {} are allowed",
+                    bar.len() / beats,
+                    MAX_PATTERN_STEPS_PER_BEAT
+                )
+            } else {
+                continue;
+            };
+            return Err(pattern::column_error(&val, column, &message));
+        }
+        Ok(Some(bars))
+    }
+
+    /// Gets the accents for each beat of the bar.
+    /// Accents can be beat numbers or a pattern of > and . with one character per beat.
+    /// Without accents, a meter accents the first beat of each bar.
+    fn get_accents(
+        accents: Option<String>,
+        meter: Option<(u8, u8)>,
+    ) -> Result<Option<Vec<bool>>, String> {
+        let param_name = "accents";
+        let (accents, beats) = match (accents, meter) {
+            (None, None) => return Ok(None),
+            (Some(_), None) => {
+                return Err("Cannot set accents without a meter.".to_string());
+            }
+            (None, Some((beats, _))) => {
+                let mut bar = vec![false; beats as usize];
+                bar[0] = true;
+                return Ok(Some(bar));
+            }
+            (Some(accents), Some((beats, _))) => (accents, beats as usize),
+        };
+
+        if accents.chars().all(|c| c == '>' || c == '.') {
+            if accents.chars().count() != beats {
+                return Err(format!(
+                    "Invalid value for {}: {}. The pattern has {} beats but the meter has {}.",
+                    param_name,
+                    accents,
+                    accents.chars().count(),
+                    beats
+                ));
+            }
+            return Ok(Some(accents.chars().map(|c| c == '>').collect()));
+        }
+
+        let mut bar = vec![false; beats];
+        for beat in helpers::parse_comma_separated::<usize>(&accents, param_name)? {
+            helpers::validate_and_extract(beat, 1, beats, param_name)?;
+            bar[beat - 1] = true;
+        }
+        Ok(Some(bar))
+    }
+
+    /// Gets the beats each chord of the progression plays for. A chord of no beats would leave nothing to play.
+    fn get_beats_per(beats_per: Option<String>) -> Result<Option<Vec<u8>>, String> {
+        let param_name = "beats-per";
+        let Some(beats) = helpers::parse_comma_separated_option::<u8>(beats_per, param_name)?
+        else {
+            return Ok(None);
+        };
+        for count in &beats {
+            helpers::validate_and_extract(*count, 1, 12, param_name)?;
+        }
+        Ok(Some(beats))
+    }
+
+    /// Gets the subdivide parameter. A 1 in a list plays the beat alone, for switching drills.
+    fn get_subdivide(subdivide: Option<String>) -> Result<Option<Vec<u8>>, String> {
+        let param_name = "subdivide";
+        let Some(subdivisions) =
+            helpers::parse_comma_separated_option::<u8>(subdivide, param_name)?
+        else {
+            return Ok(None);
+        };
+        for steps in &subdivisions {
+            helpers::validate_and_extract(*steps, 1, 6, param_name)?;
+        }
+        Ok(Some(subdivisions))
+    }
+
+    fn groups_fill_the_meter(&self) -> Result<(), String> {
+        match (&self.groups, self.meter) {
+            (Some(groups), Some((beats, unit))) if groups.iter().sum::<u8>() != beats => {
+                Err(format!(
+                    "Groups add up to {} beats, but the meter {}/{} has {}.",
+                    groups.iter().sum::<u8>(),
+                    beats,
+                    unit,
+                    beats
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn no_poly_with_other_rhythms(&self) -> Result<(), String> {
+        if self.poly.is_some()
+            && (self.pattern.is_some()
+                || self.groups.is_some()
+                || self.swing.is_some()
+                || self.drop_beats.is_some()
+                || self.subdivide.is_some()
+                || self.meter.is_some()
+                || self.harmonic)
+        {
+            Err(
+                "Cannot set poly with pattern, groups, swing, drop-beats, subdivide, meter or harmonic."
+                    .to_string(),
+            )
+        } else {
+            Ok(())
+        }
+    }
+
+    fn no_pattern_with_beat_options(&self) -> Result<(), String> {
+        if self.pattern.is_none() {
+            return Ok(());
+        }
+        if self.drop_beats.is_some() {
+            Err(
+                "Cannot set both pattern and drop-beats. Use rests in the pattern instead."
+                    .to_string(),
+            )
+        } else if self.subdivide.is_some() {
+            Err(
+                "Cannot set both pattern and subdivide. Use more steps in the pattern instead."
+                    .to_string(),
+            )
+        } else if self.accents.is_some() {
+            Err("Cannot set both pattern and accents. Use X in the pattern instead.".to_string())
+        } else if self.groups.is_some() {
+            Err("Cannot set both pattern and groups. Use X in the pattern instead.".to_string())
+        } else if self.progression.is_some() {
+            Err("Cannot set both pattern and progression.".to_string())
+        } else {
+            Ok(())
+        }
+    }
+
     fn no_tones_progression_or_beats_per_if_not_harmonic(&self) -> Result<(), String> {
         if !self.harmonic
             && (self.tones.is_some() || self.progression.is_some() || self.beats_per.is_some())
@@ -256,17 +846,6 @@ impl AppConfig {
         Ok(())
     }
 
-    fn no_simultaneous_drop_beats_and_drop_rate(&self) -> Result<(), String> {
-        if self.drop_beats.is_some() && self.drop_rate.is_some() {
-            Err(
-                "Cannot set both drop-beats and drop-rate. Please choose one or the other."
-                    .to_string(),
-            )
-        } else {
-            Ok(())
-        }
-    }
-
     fn no_drop_beats_or_rate_with_ramp(&self) -> Result<(), String> {
         if (self.drop_beats.is_some() || self.drop_rate.is_some()) && self.ramp.is_some() {
             Err("Cannot drop beats if ramp is set. Please choose one or the other.".to_string())
@@ -309,6 +888,37 @@ impl AppConfig {
         Ok(())
     }
 
+    fn transpose_cycle_requires_progression(&self) -> Result<(), String> {
+        let has_progression = matches!(self.tones, Some(Tones::Map(_)));
+        if self.transpose_cycle.is_some() && !has_progression {
+            Err("transpose-cycle requires a chord progression.".to_string())
+        } else {
+            Ok(())
+        }
+    }
+
+    fn voicing_options_valid(&self) -> Result<(), String> {
+        let Some(voicing) = &self.voicing else {
+            return Ok(());
+        };
+        let Some(progression) = &self.progression else {
+            return Err("voicing requires a chord progression.".to_string());
+        };
+        if self.voice_lead {
+            return Err(
+                "Cannot set both voicing and voice-lead. Voice leading chooses its own inversions."
+                    .to_string(),
+            );
+        }
+        if voicing.len() != 1 && voicing.len() != progression.len() {
+            return Err(
+                "If voicing is a list, it must be the same length as progression, or a single voicing."
+                    .to_string(),
+            );
+        }
+        Ok(())
+    }
+
     fn no_simultaneous_drone_and_tones(&self) -> Result<(), String> {
         if self.drone.is_some() && self.tones.is_some() {
             Err("Cannot set both drone and tones. Please choose one or the other.".to_string())
@@ -317,11 +927,107 @@ impl AppConfig {
         }
     }
 
+    fn step_options_set_together(&self) -> Result<(), String> {
+        match (self.step, self.until) {
+            (None, None) if self.every.is_some() && self.modulate.is_none() => {
+                Err("every requires step and until, or modulate.".to_string())
+            }
+            (None, None) => Ok(()),
+            (Some(_), Some(until)) if until == self.bpm => {
+                Err("until must be different from bpm for the tempo to step.".to_string())
+            }
+            (Some(_), Some(_)) if self.every.is_some() => Ok(()),
+            _ => Err("step, every and until must be set together.".to_string()),
+        }
+    }
+
+    fn modulate_options_valid(&self) -> Result<(), String> {
+        let Some(ratio) = self.modulate else {
+            return Ok(());
+        };
+        if self.step.is_some() || self.ramp.is_some() {
+            return Err("Cannot set modulate with step or ramp. Please choose one.".to_string());
+        }
+        let Some(every) = self.every else {
+            return Err("modulate requires every.".to_string());
+        };
+        let bar = self.beats_per_bar() as u32;
+        if !every.is_multiple_of(bar) {
+            return Err(format!(
+                "every must be a whole number of bars for modulate, but {} beats is not a multiple of {}.",
+                every, bar
+            ));
+        }
+        helpers::validate_and_extract(
+            modulation::modulated_bpm(self.bpm, ratio),
+            MIN_BPM,
+            MAX_BPM,
+            "modulated bpm",
+        )?;
+        Ok(())
+    }
+
+    fn no_step_with_ramp(&self) -> Result<(), String> {
+        if self.step.is_some() && self.ramp.is_some() {
+            Err("Cannot set both step and ramp. Please choose one or the other.".to_string())
+        } else {
+            Ok(())
+        }
+    }
+
     fn change_rate_warning(&self) {
         if self.change_rate.is_some() && self.ramp.is_none() {
             eprintln!("Warning: change-rate is set but ramp is not. change-rate will be ignored.");
         }
     }
+
+    fn ramp_shape_warning(&self) {
+        if self.ramp_shape != RampShape::Bounce && self.ramp.is_none() {
+            eprintln!("Warning: ramp-shape is set but ramp is not. ramp-shape will be ignored.");
+        }
+    }
+
+    fn at_end_warning(&self) {
+        if self.at_end != StepEnd::Hold && self.step.is_none() {
+            eprintln!("Warning: at-end is set but step is not. at-end will be ignored.");
+        }
+    }
+
+    fn seed_warning(&self) {
+        let drop_beats_ranged = self
+            .drop_beats
+            .is_some_and(|(on, off)| on.0 != on.1 || off.0 != off.1);
+        let random_keys = self.transpose_cycle == Some(TransposeCycle::Random);
+        if self.seed.is_some() && self.drop_rate.is_none() && !drop_beats_ranged && !random_keys {
+            eprintln!(
+                "Warning: seed is set but neither drop-rate, a drop-beats range nor a random transpose-cycle is. seed will be ignored."
+            );
+        }
+    }
+
+    fn swing_warnings(&self) {
+        if self.swing.is_some() && self.subdivide.is_none() && self.pattern.is_none() {
+            eprintln!("Warning: swing is set but subdivide and pattern are not. swing will have no effect.");
+        }
+        if self.swing_curve && self.swing.is_none() {
+            eprintln!("Warning: swing-curve is set but swing is not. swing-curve will be ignored.");
+        }
+    }
+
+    fn voice_lead_warning(&self) {
+        if self.voice_lead && !matches!(self.tones, Some(Tones::Map(_))) {
+            eprintln!(
+                "Warning: voice-lead is set but progression is not. voice-lead will be ignored."
+            );
+        }
+    }
+
+    fn subdivide_every_warning(&self) {
+        let switches = self.subdivide.as_ref().is_some_and(|list| list.len() > 1);
+        if self.subdivide_every.is_some() && !switches {
+            eprintln!("Warning: subdivide-every is set but subdivide is not a list. subdivide-every will be ignored.");
+        }
+    }
 }
 
 mod tests {
@@ -331,13 +1037,35 @@ mod tests {
     #[fixture]
     fn base_cli() -> CliOptions {
         CliOptions {
-            bpm: 120,
+            bpm: 120.0,
             drop_beats: None,
             drop_rate: None,
+            seed: None,
+            subdivide: None,
+            subdivide_every: None,
+            meter: None,
+            accents: None,
+            groups: None,
+            pattern: None,
+            swing: None,
+            swing_curve: false,
+            poly: None,
             ramp: None,
             change_rate: None,
+            ramp_shape: None,
+            step: None,
+            every: None,
+            until: None,
+            at_end: None,
+            modulate: None,
             drone: None,
             tones: None,
+            register: None,
+            key: None,
+            mode: None,
+            transpose_cycle: None,
+            voice_lead: false,
+            voicing: None,
             progression: None,
             beats_per: None,
             harmonic: false,
@@ -347,7 +1075,27 @@ mod tests {
     #[rstest]
     fn bpm_is_a_number(base_cli: CliOptions) {
         let config = super::AppConfig::from_cli(base_cli).unwrap();
-        assert_eq!(config.bpm, 120);
+        assert_eq!(config.bpm, 120.0);
+    }
+
+    #[rstest]
+    #[case(92.5)]
+    #[case(10.0)]
+    #[case(500.0)]
+    fn bpm_accepts_decimals_in_range(base_cli: CliOptions, #[case] bpm: f64) {
+        let cli = CliOptions { bpm, ..base_cli };
+        let config = super::AppConfig::from_cli(cli).unwrap();
+        assert_eq!(config.bpm, bpm);
+    }
+
+    #[rstest]
+    #[case(9.99)]
+    #[case(500.5)]
+    #[case(f64::NAN)]
+    fn bpm_fails_outside_range(base_cli: CliOptions, #[case] bpm: f64) {
+        let cli = CliOptions { bpm, ..base_cli };
+        let config = super::AppConfig::from_cli(cli);
+        assert!(config.is_err());
     }
 
     #[rstest]
@@ -357,7 +1105,7 @@ mod tests {
             ..base_cli
         };
         let config = super::AppConfig::from_cli(cli).unwrap();
-        assert_eq!(config.drop_beats, Some((4, 8)));
+        assert_eq!(config.drop_beats, Some(((4, 4), (8, 8))));
     }
 
     #[rstest]
@@ -367,7 +1115,7 @@ mod tests {
             ..base_cli
         };
         let config = super::AppConfig::from_cli(cli).unwrap();
-        assert_eq!(config.drop_beats, Some((4, 4)));
+        assert_eq!(config.drop_beats, Some(((4, 4), (4, 4))));
     }
 
     #[rstest]
@@ -376,6 +1124,10 @@ mod tests {
     #[case("1,25")]
     #[case("-1")]
     #[case("0")]
+    #[case("4,8-2")]
+    #[case("4,2-25")]
+    #[case("4,2-")]
+    #[case("4,2-4-8")]
     fn drop_beats_fails_on_invalid_input(base_cli: CliOptions, #[case] input: &str) {
         let cli = CliOptions {
             drop_beats: Some(String::from(input)),
@@ -385,6 +1137,24 @@ mod tests {
         assert!(config.is_err());
     }
 
+    #[rstest]
+    #[case("4,2-8", ((4, 4), (2, 8)))]
+    #[case("2-4,4-12", ((2, 4), (4, 12)))]
+    #[case("2-4", ((2, 4), (2, 4)))]
+    #[case("3-3,5", ((3, 3), (5, 5)))]
+    fn drop_beats_accepts_ranges(
+        base_cli: CliOptions,
+        #[case] input: &str,
+        #[case] expected: ((u8, u8), (u8, u8)),
+    ) {
+        let cli = CliOptions {
+            drop_beats: Some(String::from(input)),
+            ..base_cli
+        };
+        let config = super::AppConfig::from_cli(cli).unwrap();
+        assert_eq!(config.drop_beats, Some(expected));
+    }
+
     #[rstest]
     fn drop_rate_is_a_number(base_cli: CliOptions) {
         let cli = CliOptions {
@@ -396,74 +1166,373 @@ mod tests {
     }
 
     #[rstest]
-    fn ramp_is_a_number(base_cli: CliOptions) {
+    #[case("2", vec![2])]
+    #[case("3", vec![3])]
+    #[case("2,3", vec![2, 3])]
+    #[case("1, 2, 4", vec![1, 2, 4])]
+    fn subdivide_is_a_list(base_cli: CliOptions, #[case] input: &str, #[case] expected: Vec<u8>) {
         let cli = CliOptions {
-            ramp: Some(150),
+            subdivide: Some(String::from(input)),
             ..base_cli
         };
         let config = super::AppConfig::from_cli(cli).unwrap();
-        assert_eq!(config.ramp, Some(150));
+        assert_eq!(config.subdivide, Some(expected));
     }
 
     #[rstest]
-    fn change_rate_is_a_number(base_cli: CliOptions) {
+    #[case("0")]
+    #[case("7")]
+    #[case("2,x")]
+    fn subdivide_fails_on_invalid_input(base_cli: CliOptions, #[case] input: &str) {
         let cli = CliOptions {
-            change_rate: Some(50),
+            subdivide: Some(String::from(input)),
             ..base_cli
         };
-        let config = super::AppConfig::from_cli(cli).unwrap();
-        assert_eq!(config.change_rate, Some(50));
+        let config = super::AppConfig::from_cli(cli);
+        assert!(config.is_err());
     }
 
     #[rstest]
-    #[case("A2", vec!["A2"])]
-    #[case("A#5,Ab2,Bb3,C#4", vec!["A#5", "Ab2", "Bb3", "C#4"])]
-    fn drone_is_a_vec_of_strings(
+    #[case("4/4", (4, 4))]
+    #[case("7/8", (7, 8))]
+    #[case(" 5 / 16 ", (5, 16))]
+    fn meter_is_beats_and_unit(
         base_cli: CliOptions,
-        #[case] drone: &str,
-        #[case] expected: Vec<&str>,
+        #[case] input: &str,
+        #[case] expected: (u8, u8),
     ) {
         let cli = CliOptions {
-            drone: Some(String::from(drone)),
+            meter: Some(String::from(input)),
             ..base_cli
         };
         let config = super::AppConfig::from_cli(cli).unwrap();
-        assert_eq!(
-            config.drone,
-            Some(expected.iter().map(|s| s.to_string()).collect())
-        );
+        assert_eq!(config.meter, Some(expected));
     }
 
     #[rstest]
-    #[case("H")]
-    #[case("A3,B3,C3,Z3")]
-    #[case("A3,B3,C3,H3")]
-    #[case("A3,B3,C3,D3,E3")]
-    #[case("A,B3,C3")]
-    #[case("A&")]
-    #[case("Ab9")]
-    fn get_drone_fails_if_note_is_invalid(base_cli: CliOptions, #[case] drone: &str) {
+    #[case("4")]
+    #[case("0/4")]
+    #[case("7/6")]
+    #[case("a/b")]
+    fn meter_fails_on_invalid_input(base_cli: CliOptions, #[case] input: &str) {
         let cli = CliOptions {
-            drone: Some(String::from(drone)),
-            harmonic: true,
+            meter: Some(String::from(input)),
             ..base_cli
         };
         let config = super::AppConfig::from_cli(cli);
-        println!("{:?}", config);
         assert!(config.is_err());
     }
 
     #[rstest]
-    #[case("A2", vec!["A2"])]
-    #[case("A#5,Ab2,Bb3,C#4", vec!["A#5", "Ab2", "Bb3", "C#4"])]
-    fn tones_list_is_a_vec_of_strings(
+    #[case(None, vec![true, false, false, false, false, false, false])]
+    #[case(Some("1,4"), vec![true, false, false, true, false, false, false])]
+    #[case(Some(">..>.>."), vec![true, false, false, true, false, true, false])]
+    #[case(Some("......."), vec![false; 7])]
+    fn accents_mark_beats_of_the_bar(
         base_cli: CliOptions,
-        #[case] tones: &str,
-        #[case] expected: Vec<&str>,
+        #[case] input: Option<&str>,
+        #[case] expected: Vec<bool>,
     ) {
         let cli = CliOptions {
-            tones: Some(String::from(tones)),
-            harmonic: true,
+            meter: Some(String::from("7/8")),
+            accents: input.map(String::from),
+            ..base_cli
+        };
+        let config = super::AppConfig::from_cli(cli).unwrap();
+        assert_eq!(config.accents, Some(expected));
+    }
+
+    #[rstest]
+    #[case(Some("7/8"), "0")]
+    #[case(Some("7/8"), "8")]
+    #[case(Some("7/8"), ">..>")]
+    #[case(Some("7/8"), ">x.>...")]
+    #[case(None, "1")]
+    fn accents_fail_on_invalid_input(
+        base_cli: CliOptions,
+        #[case] meter: Option<&str>,
+        #[case] input: &str,
+    ) {
+        let cli = CliOptions {
+            meter: meter.map(String::from),
+            accents: Some(String::from(input)),
+            ..base_cli
+        };
+        let config = super::AppConfig::from_cli(cli);
+        assert!(config.is_err());
+    }
+
+    #[rstest]
+    fn no_accents_without_meter(base_cli: CliOptions) {
+        let config = super::AppConfig::from_cli(base_cli).unwrap();
+        assert_eq!(config.accents, None);
+    }
+
+    #[rstest]
+    fn pattern_is_bars_of_steps(base_cli: CliOptions) {
+        use crate::pattern::Step::*;
+        let cli = CliOptions {
+            pattern: Some(String::from("x..X|g.x.")),
+            ..base_cli
+        };
+        let config = super::AppConfig::from_cli(cli).unwrap();
+        assert_eq!(
+            config.pattern,
+            Some(vec![
+                vec![Hit, Rest, Rest, Accent],
+                vec![Ghost, Rest, Hit, Rest]
+            ])
+        );
+        assert_eq!(config.accents, None);
+    }
+
+    #[rstest]
+    #[case(None, "x..x..x.|x.x.x.")]
+    #[case(Some("3/4"), "x...")]
+    #[case(Some("1/4"), "x................")]
+    fn pattern_bars_must_fit_the_meter(
+        base_cli: CliOptions,
+        #[case] meter: Option<&str>,
+        #[case] input: &str,
+    ) {
+        let cli = CliOptions {
+            meter: meter.map(String::from),
+            pattern: Some(String::from(input)),
+            ..base_cli
+        };
+        let err = super::AppConfig::from_cli(cli).unwrap_err();
+        assert!(
+            err.starts_with("Invalid value for pattern at column"),
+            "{}",
+            err
+        );
+    }
+
+    #[rstest]
+    fn pattern_error_points_at_the_bar(base_cli: CliOptions) {
+        let cli = CliOptions {
+            pattern: Some(String::from("x.x.x.x.|x.x")),
+            ..base_cli
+        };
+        let err = super::AppConfig::from_cli(cli).unwrap_err();
+        assert!(
+            err.starts_with("Invalid value for pattern at column 10:"),
+            "{}",
+            err
+        );
+    }
+
+    #[rstest]
+    fn no_pattern_with_drop_beats(base_cli: CliOptions) {
+        let cli = CliOptions {
+            pattern: Some(String::from("x.x.")),
+            drop_beats: Some(String::from("4")),
+            ..base_cli
+        };
+        let config = super::AppConfig::from_cli(cli);
+        assert!(config.is_err());
+    }
+
+    #[rstest]
+    #[case("3:2", (3, 2))]
+    #[case("7:4", (7, 4))]
+    #[case(" 5 : 4 ", (5, 4))]
+    fn poly_is_a_ratio(base_cli: CliOptions, #[case] input: &str, #[case] expected: (u8, u8)) {
+        let cli = CliOptions {
+            poly: Some(String::from(input)),
+            ..base_cli
+        };
+        let config = super::AppConfig::from_cli(cli).unwrap();
+        assert_eq!(config.poly, Some(expected));
+    }
+
+    #[rstest]
+    #[case("3")]
+    #[case("3/2")]
+    #[case("0:2")]
+    #[case("17:4")]
+    fn poly_fails_on_invalid_input(base_cli: CliOptions, #[case] input: &str) {
+        let cli = CliOptions {
+            poly: Some(String::from(input)),
+            ..base_cli
+        };
+        let config = super::AppConfig::from_cli(cli);
+        assert!(config.is_err());
+    }
+
+    #[rstest]
+    fn no_poly_with_subdivide(base_cli: CliOptions) {
+        let cli = CliOptions {
+            poly: Some(String::from("3:2")),
+            subdivide: Some(String::from("2")),
+            ..base_cli
+        };
+        let config = super::AppConfig::from_cli(cli);
+        assert!(config.is_err());
+    }
+
+    #[rstest]
+    #[case("2+2+3", vec![2, 2, 3])]
+    #[case("3 + 3 + 2 + 2 + 2", vec![3, 3, 2, 2, 2])]
+    #[case("4", vec![4])]
+    fn groups_are_sizes(base_cli: CliOptions, #[case] input: &str, #[case] expected: Vec<u8>) {
+        let cli = CliOptions {
+            groups: Some(String::from(input)),
+            ..base_cli
+        };
+        let config = super::AppConfig::from_cli(cli).unwrap();
+        assert_eq!(config.beats_per_bar(), expected.iter().sum::<u8>());
+        assert_eq!(config.groups, Some(expected));
+    }
+
+    #[rstest]
+    #[case(None, "2+0+3")]
+    #[case(None, "2,2,3")]
+    #[case(None, "16+16+1")]
+    #[case(Some("7/8"), "2+2+2")]
+    fn groups_fail_on_invalid_input(
+        base_cli: CliOptions,
+        #[case] meter: Option<&str>,
+        #[case] input: &str,
+    ) {
+        let cli = CliOptions {
+            meter: meter.map(String::from),
+            groups: Some(String::from(input)),
+            ..base_cli
+        };
+        let config = super::AppConfig::from_cli(cli);
+        assert!(config.is_err());
+    }
+
+    #[rstest]
+    #[case("60", 0.6)]
+    #[case("50", 0.5)]
+    #[case("2:1", 2.0 / 3.0)]
+    #[case("3:1", 0.75)]
+    fn swing_is_a_share_of_each_pair(
+        base_cli: CliOptions,
+        #[case] input: &str,
+        #[case] expected: f64,
+    ) {
+        let cli = CliOptions {
+            subdivide: Some(String::from("2")),
+            swing: Some(String::from(input)),
+            ..base_cli
+        };
+        let config = super::AppConfig::from_cli(cli).unwrap();
+        assert!((config.swing.unwrap() - expected).abs() < 1e-9);
+    }
+
+    #[rstest]
+    #[case("40")]
+    #[case("80")]
+    #[case("1:2")]
+    #[case("2:0")]
+    #[case("swung")]
+    fn swing_fails_on_invalid_input(base_cli: CliOptions, #[case] input: &str) {
+        let cli = CliOptions {
+            swing: Some(String::from(input)),
+            ..base_cli
+        };
+        let config = super::AppConfig::from_cli(cli);
+        assert!(config.is_err());
+    }
+
+    #[rstest]
+    fn seed_is_a_number(base_cli: CliOptions) {
+        let cli = CliOptions {
+            drop_rate: Some(50),
+            seed: Some(42),
+            ..base_cli
+        };
+        let config = super::AppConfig::from_cli(cli).unwrap();
+        assert_eq!(config.seed, Some(42));
+    }
+
+    #[rstest]
+    fn ramp_is_a_number(base_cli: CliOptions) {
+        let cli = CliOptions {
+            ramp: Some(150.5),
+            ..base_cli
+        };
+        let config = super::AppConfig::from_cli(cli).unwrap();
+        assert_eq!(config.ramp, Some(150.5));
+    }
+
+    #[rstest]
+    #[case(50.0)]
+    #[case(0.5)]
+    fn change_rate_accepts_decimals_in_range(base_cli: CliOptions, #[case] change_rate: f64) {
+        let cli = CliOptions {
+            change_rate: Some(change_rate),
+            ..base_cli
+        };
+        let config = super::AppConfig::from_cli(cli).unwrap();
+        assert_eq!(config.change_rate, Some(change_rate));
+    }
+
+    #[rstest]
+    #[case(0.0)]
+    #[case(0.05)]
+    #[case(100.0)]
+    #[case(f64::NAN)]
+    fn change_rate_fails_outside_range(base_cli: CliOptions, #[case] change_rate: f64) {
+        let cli = CliOptions {
+            change_rate: Some(change_rate),
+            ..base_cli
+        };
+        assert!(super::AppConfig::from_cli(cli).is_err());
+    }
+
+    #[rstest]
+    #[case("A2", vec!["A2"])]
+    #[case("A#5,Ab2,Bb3,C#4", vec!["A#5", "Ab2", "Bb3", "C#4"])]
+    fn drone_is_a_vec_of_strings(
+        base_cli: CliOptions,
+        #[case] drone: &str,
+        #[case] expected: Vec<&str>,
+    ) {
+        let cli = CliOptions {
+            drone: Some(String::from(drone)),
+            ..base_cli
+        };
+        let config = super::AppConfig::from_cli(cli).unwrap();
+        assert_eq!(
+            config.drone,
+            Some(expected.iter().map(|s| s.to_string()).collect())
+        );
+    }
+
+    #[rstest]
+    #[case("H")]
+    #[case("A3,B3,C3,Z3")]
+    #[case("A3,B3,C3,H3")]
+    #[case("A3,B3,C3,D3,E3")]
+    #[case("A,B3,C3")]
+    #[case("A&")]
+    #[case("Ab9")]
+    fn get_drone_fails_if_note_is_invalid(base_cli: CliOptions, #[case] drone: &str) {
+        let cli = CliOptions {
+            drone: Some(String::from(drone)),
+            harmonic: true,
+            ..base_cli
+        };
+        let config = super::AppConfig::from_cli(cli);
+        println!("{:?}", config);
+        assert!(config.is_err());
+    }
+
+    #[rstest]
+    #[case("A2", vec!["A2"])]
+    #[case("A#5,Ab2,Bb3,C#4", vec!["A#5", "Ab2", "Bb3", "C#4"])]
+    fn tones_list_is_a_vec_of_strings(
+        base_cli: CliOptions,
+        #[case] tones: &str,
+        #[case] expected: Vec<&str>,
+    ) {
+        let cli = CliOptions {
+            tones: Some(String::from(tones)),
+            harmonic: true,
             ..base_cli
         };
         let config = super::AppConfig::from_cli(cli).unwrap();
@@ -579,6 +1648,203 @@ mod tests {
         assert_eq!(config.beats_per, Some(vec![4, 3, 2]));
     }
 
+    #[rstest]
+    fn tones_accept_chord_symbols(base_cli: CliOptions) {
+        let cli = CliOptions {
+            tones: Some(String::from("Cmaj7,G7")),
+            progression: Some(String::from("Cmaj7,G7")),
+            beats_per: Some(String::from("4")),
+            harmonic: true,
+            ..base_cli
+        };
+        let config = super::AppConfig::from_cli(cli).unwrap();
+        let Some(super::Tones::Map(map)) = config.tones else {
+            panic!("expected a tone map");
+        };
+        assert_eq!(map["Cmaj7"], vec!["C3", "E3", "G3", "B3"]);
+        assert_eq!(map["G7"], vec!["G3", "B3", "D4", "F4"]);
+    }
+
+    #[rstest]
+    fn progression_of_chord_symbols_needs_no_tones(base_cli: CliOptions) {
+        let cli = CliOptions {
+            progression: Some(String::from("Dm7,G7,Cmaj7")),
+            beats_per: Some(String::from("4")),
+            register: Some(2),
+            harmonic: true,
+            ..base_cli
+        };
+        let config = super::AppConfig::from_cli(cli).unwrap();
+        let Some(super::Tones::Map(map)) = config.tones else {
+            panic!("expected a tone map");
+        };
+        assert_eq!(map.len(), 3);
+        assert_eq!(map["Dm7"], vec!["D2", "F2", "A2", "C3"]);
+    }
+
+    #[rstest]
+    fn unknown_chord_symbol_is_named(base_cli: CliOptions) {
+        let cli = CliOptions {
+            progression: Some(String::from("Dm7,G7,Cmaj8")),
+            beats_per: Some(String::from("4")),
+            harmonic: true,
+            ..base_cli
+        };
+        let err = super::AppConfig::from_cli(cli).unwrap_err();
+        assert!(err.starts_with("Unknown chord symbol Cmaj8"), "{}", err);
+    }
+
+    #[rstest]
+    #[case(1)]
+    #[case(6)]
+    fn register_fails_out_of_range(base_cli: CliOptions, #[case] register: u8) {
+        let cli = CliOptions {
+            progression: Some(String::from("Dm7,G7")),
+            beats_per: Some(String::from("4")),
+            register: Some(register),
+            harmonic: true,
+            ..base_cli
+        };
+        assert!(super::AppConfig::from_cli(cli).is_err());
+    }
+
+    #[rstest]
+    #[case("Eb", None, "ii7,V7,Imaj7,vi7", "ii7", &["F3", "Ab3", "C4", "Eb4"])]
+    #[case("G", None, "1,4,5,1", "5", &["D3", "F#3", "A3"])]
+    #[case("C", Some(crate::cli::Mode::Minor), "i,iv,III", "III", &["Eb3", "G3", "Bb3"])]
+    fn key_resolves_numerals(
+        base_cli: CliOptions,
+        #[case] key: &str,
+        #[case] mode: Option<crate::cli::Mode>,
+        #[case] progression: &str,
+        #[case] numeral: &str,
+        #[case] expected: &[&str],
+    ) {
+        let cli = CliOptions {
+            key: Some(String::from(key)),
+            mode,
+            progression: Some(String::from(progression)),
+            beats_per: Some(String::from("4")),
+            harmonic: true,
+            ..base_cli
+        };
+        let config = super::AppConfig::from_cli(cli).unwrap();
+        let Some(super::Tones::Map(map)) = config.tones else {
+            panic!("expected a tone map");
+        };
+        assert_eq!(map[numeral], expected);
+    }
+
+    #[rstest]
+    fn transpose_cycle_accepts_a_progression(base_cli: CliOptions) {
+        let cli = CliOptions {
+            progression: Some(String::from("Dm7,G7,Cmaj7")),
+            beats_per: Some(String::from("4")),
+            transpose_cycle: Some(crate::cli::TransposeCycle::Fourths),
+            harmonic: true,
+            ..base_cli
+        };
+        let config = super::AppConfig::from_cli(cli).unwrap();
+        assert_eq!(
+            config.transpose_cycle,
+            Some(crate::cli::TransposeCycle::Fourths)
+        );
+    }
+
+    #[rstest]
+    fn transpose_cycle_requires_a_progression(base_cli: CliOptions) {
+        let cli = CliOptions {
+            tones: Some(String::from("C4 E4 G4")),
+            transpose_cycle: Some(crate::cli::TransposeCycle::Fifths),
+            harmonic: true,
+            ..base_cli
+        };
+        assert_eq!(
+            super::AppConfig::from_cli(cli).unwrap_err(),
+            "transpose-cycle requires a chord progression."
+        );
+    }
+
+    #[rstest]
+    fn voice_lead_is_a_flag(base_cli: CliOptions) {
+        let cli = CliOptions {
+            progression: Some(String::from("Dm7,G7,Cmaj7")),
+            beats_per: Some(String::from("4")),
+            voice_lead: true,
+            harmonic: true,
+            ..base_cli
+        };
+        let config = super::AppConfig::from_cli(cli).unwrap();
+        assert!(config.voice_lead);
+    }
+
+    #[rstest]
+    #[case(vec![crate::cli::Voicing::Drop2])]
+    #[case(vec![crate::cli::Voicing::Shell, crate::cli::Voicing::Root, crate::cli::Voicing::Spread])]
+    fn voicing_is_global_or_per_chord(
+        base_cli: CliOptions,
+        #[case] voicing: Vec<crate::cli::Voicing>,
+    ) {
+        let cli = CliOptions {
+            progression: Some(String::from("Dm7,G7,Cmaj7")),
+            beats_per: Some(String::from("4")),
+            voicing: Some(voicing.clone()),
+            harmonic: true,
+            ..base_cli
+        };
+        let config = super::AppConfig::from_cli(cli).unwrap();
+        assert_eq!(config.voicing, Some(voicing));
+    }
+
+    #[rstest]
+    #[case(Some("Dm7,G7,Cmaj7"), vec![crate::cli::Voicing::Root, crate::cli::Voicing::Shell], false, "If voicing is a list, it must be the same length as progression, or a single voicing.")]
+    #[case(Some("Dm7,G7,Cmaj7"), vec![crate::cli::Voicing::Drop2], true, "Cannot set both voicing and voice-lead. Voice leading chooses its own inversions.")]
+    #[case(None, vec![crate::cli::Voicing::Drop2], false, "voicing requires a chord progression.")]
+    fn invalid_voicing_fails(
+        base_cli: CliOptions,
+        #[case] progression: Option<&str>,
+        #[case] voicing: Vec<crate::cli::Voicing>,
+        #[case] voice_lead: bool,
+        #[case] expected: &str,
+    ) {
+        let cli = CliOptions {
+            tones: progression.is_none().then(|| String::from("C4 E4 G4")),
+            progression: progression.map(String::from),
+            beats_per: progression.map(|_| String::from("4")),
+            voicing: Some(voicing),
+            voice_lead,
+            harmonic: true,
+            ..base_cli
+        };
+        assert_eq!(super::AppConfig::from_cli(cli).unwrap_err(), expected);
+    }
+
+    #[rstest]
+    fn no_key_with_tones(base_cli: CliOptions) {
+        let cli = CliOptions {
+            key: Some(String::from("C")),
+            tones: Some(String::from("I(C3 E3 G3)")),
+            progression: Some(String::from("I")),
+            beats_per: Some(String::from("4")),
+            harmonic: true,
+            ..base_cli
+        };
+        assert!(super::AppConfig::from_cli(cli).is_err());
+    }
+
+    #[rstest]
+    fn key_rejects_chord_symbols(base_cli: CliOptions) {
+        let cli = CliOptions {
+            key: Some(String::from("C")),
+            progression: Some(String::from("Dm7,G7")),
+            beats_per: Some(String::from("4")),
+            harmonic: true,
+            ..base_cli
+        };
+        let err = super::AppConfig::from_cli(cli).unwrap_err();
+        assert!(err.starts_with("Invalid numeral Dm7"), "{}", err);
+    }
+
     #[rstest]
     fn progression_fails_if_len_over_24(base_cli: CliOptions) {
         let cli = CliOptions {
@@ -615,6 +1881,24 @@ mod tests {
         assert_eq!(config.beats_per, Some(vec![4]));
     }
 
+    #[rstest]
+    #[case("0", "Invalid value for beats-per: 0 is outside the range [1, 12]")]
+    #[case("4,0,2", "Invalid value for beats-per: 0 is outside the range [1, 12]")]
+    #[case("13", "Invalid value for beats-per: 13 is outside the range [1, 12]")]
+    fn beats_per_out_of_range_fails(
+        base_cli: CliOptions,
+        #[case] beats_per: &str,
+        #[case] expected: &str,
+    ) {
+        let cli = CliOptions {
+            progression: Some(String::from("Cmaj,Dmin,E7")),
+            beats_per: Some(String::from(beats_per)),
+            harmonic: true,
+            ..base_cli
+        };
+        assert_eq!(super::AppConfig::from_cli(cli).unwrap_err(), expected);
+    }
+
     #[rstest]
     fn progression_and_beats_per_length_mismatch(base_cli: CliOptions) {
         let cli = CliOptions {
@@ -651,27 +1935,166 @@ mod tests {
     }
 
     #[rstest]
-    fn no_simultaneous_drop_beats_and_drop_rate(base_cli: CliOptions) {
+    fn drop_beats_and_drop_rate_combine(base_cli: CliOptions) {
         let cli = CliOptions {
             drop_beats: Some(String::from("4,8")),
             drop_rate: Some(50),
             ..base_cli
         };
-        let config = super::AppConfig::from_cli(cli);
-        assert!(config.is_err());
+        let config = super::AppConfig::from_cli(cli).unwrap();
+        assert_eq!(config.drop_beats, Some(((4, 4), (8, 8))));
+        assert_eq!(config.drop_rate, Some(50));
     }
 
     #[rstest]
     fn no_drop_beats_or_rate_with_ramp(base_cli: CliOptions) {
         let cli = CliOptions {
             drop_beats: Some(String::from("4,8")),
-            ramp: Some(150),
+            ramp: Some(150.0),
             ..base_cli
         };
         let config = super::AppConfig::from_cli(cli);
         assert!(config.is_err());
     }
 
+    #[rstest]
+    #[case("8", None, 8)]
+    #[case("8beats", None, 8)]
+    #[case("2bars", None, 8)]
+    #[case("1bar", Some("7/8"), 7)]
+    fn every_counts_beats_or_bars(
+        base_cli: CliOptions,
+        #[case] every: &str,
+        #[case] meter: Option<&str>,
+        #[case] expected: u32,
+    ) {
+        let cli = CliOptions {
+            step: Some(5.0),
+            every: Some(String::from(every)),
+            until: Some(160.0),
+            meter: meter.map(String::from),
+            ..base_cli
+        };
+        let config = super::AppConfig::from_cli(cli).unwrap();
+        assert_eq!(config.every, Some(expected));
+    }
+
+    #[rstest]
+    #[case("0")]
+    #[case("65")]
+    #[case("2 measures")]
+    #[case("bars")]
+    fn every_fails_on_invalid_input(base_cli: CliOptions, #[case] every: &str) {
+        let cli = CliOptions {
+            step: Some(5.0),
+            every: Some(String::from(every)),
+            until: Some(160.0),
+            ..base_cli
+        };
+        assert!(super::AppConfig::from_cli(cli).is_err());
+    }
+
+    #[rstest]
+    #[case(Some(5.0), Some("8"), None)]
+    #[case(Some(5.0), None, Some(160.0))]
+    #[case(None, Some("8"), Some(160.0))]
+    fn step_options_must_be_set_together(
+        base_cli: CliOptions,
+        #[case] step: Option<f64>,
+        #[case] every: Option<&str>,
+        #[case] until: Option<f64>,
+    ) {
+        let cli = CliOptions {
+            step,
+            every: every.map(String::from),
+            until,
+            ..base_cli
+        };
+        assert!(super::AppConfig::from_cli(cli).is_err());
+    }
+
+    #[rstest]
+    fn until_must_differ_from_bpm(base_cli: CliOptions) {
+        let cli = CliOptions {
+            step: Some(5.0),
+            every: Some(String::from("8")),
+            until: Some(base_cli.bpm),
+            ..base_cli
+        };
+        assert!(super::AppConfig::from_cli(cli).is_err());
+    }
+
+    #[rstest]
+    fn no_step_with_ramp(base_cli: CliOptions) {
+        let cli = CliOptions {
+            step: Some(5.0),
+            every: Some(String::from("8")),
+            until: Some(160.0),
+            ramp: Some(150.0),
+            ..base_cli
+        };
+        assert!(super::AppConfig::from_cli(cli).is_err());
+    }
+
+    #[rstest]
+    #[case("3:2", (3, 2))]
+    #[case(" 1 : 3 ", (1, 3))]
+    fn modulate_is_a_ratio(
+        base_cli: CliOptions,
+        #[case] modulate: &str,
+        #[case] expected: (u8, u8),
+    ) {
+        let cli = CliOptions {
+            modulate: Some(String::from(modulate)),
+            every: Some(String::from("2bars")),
+            ..base_cli
+        };
+        let config = super::AppConfig::from_cli(cli).unwrap();
+        assert_eq!(config.modulate, Some(expected));
+    }
+
+    #[rstest]
+    #[case("3", Some("8"))]
+    #[case("0:2", Some("8"))]
+    #[case("3:17", Some("8"))]
+    #[case("2:2", Some("8"))]
+    #[case("3:2", None)]
+    #[case("3:2", Some("6"))]
+    #[case("1:16", Some("8"))]
+    fn modulate_fails_on_invalid_input(
+        base_cli: CliOptions,
+        #[case] modulate: &str,
+        #[case] every: Option<&str>,
+    ) {
+        let cli = CliOptions {
+            modulate: Some(String::from(modulate)),
+            every: every.map(String::from),
+            ..base_cli
+        };
+        assert!(super::AppConfig::from_cli(cli).is_err());
+    }
+
+    #[rstest]
+    fn every_requires_step_or_modulate(base_cli: CliOptions) {
+        let cli = CliOptions {
+            every: Some(String::from("8")),
+            ..base_cli
+        };
+        assert!(super::AppConfig::from_cli(cli).is_err());
+    }
+
+    #[rstest]
+    fn no_modulate_with_step(base_cli: CliOptions) {
+        let cli = CliOptions {
+            modulate: Some(String::from("3:2")),
+            step: Some(5.0),
+            every: Some(String::from("8")),
+            until: Some(160.0),
+            ..base_cli
+        };
+        assert!(super::AppConfig::from_cli(cli).is_err());
+    }
+
     #[rstest]
     fn progression_requires_beats_per(base_cli: CliOptions) {
         let cli = CliOptions {
@@ -693,4 +2116,36 @@ mod tests {
         let config = super::AppConfig::from_cli(cli);
         assert!(config.is_err());
     }
+
+    #[rstest]
+    fn render_duration_is_in_seconds(base_cli: CliOptions) {
+        let cli = crate::cli::RenderOptions {
+            metronome: base_cli,
+            duration: String::from("1m30s"),
+            out: std::path::PathBuf::from("click.wav"),
+            sample_rate: 48000,
+        };
+        let config = super::RenderConfig::from_cli(cli).unwrap();
+        assert_eq!(config.duration, 90.0);
+        assert_eq!(config.sample_rate, 48000);
+    }
+
+    #[rstest]
+    #[case("0", 44100)]
+    #[case("2m", 4000)]
+    #[case("2m", 200000)]
+    fn render_fails_on_invalid_input(
+        base_cli: CliOptions,
+        #[case] duration: &str,
+        #[case] sample_rate: u32,
+    ) {
+        let cli = crate::cli::RenderOptions {
+            metronome: base_cli,
+            duration: String::from(duration),
+            out: std::path::PathBuf::from("click.wav"),
+            sample_rate,
+        };
+        let config = super::RenderConfig::from_cli(cli);
+        assert!(config.is_err());
+    }
 }
@@ -1,3 +1,44 @@
+/// Lowest supported tempo in beats per minute.
+pub const MIN_BPM: f64 = 10.0;
+
+/// Highest supported tempo in beats per minute.
+pub const MAX_BPM: f64 = 500.0;
+
+/// Number of beats in a bar when no meter is given, such as for switching subdivisions.
+pub const DEFAULT_BEATS_PER_BAR: u8 = 4;
+
+/// Most steps a `--pattern` can fit into one beat.
+pub const MAX_PATTERN_STEPS_PER_BEAT: usize = 16;
+
+/// Straight timing, as the percentage of each pair of steps given to the first.
+pub const MIN_SWING: f64 = 50.0;
+
+/// Heaviest supported swing, a 3:1 ratio.
+pub const MAX_SWING: f64 = 75.0;
+
+/// Sample rate used for files and other outputs without a device, in Hz.
+pub const DEFAULT_SAMPLE_RATE: u32 = 44100;
+
+/// Lowest sample rate accepted for playback and rendered files, in Hz.
+pub const MIN_SAMPLE_RATE: u32 = 8000;
+
+/// Highest sample rate accepted for playback and rendered files, in Hz.
+pub const MAX_SAMPLE_RATE: u32 = 192000;
+
+/// Smallest device buffer size accepted, in frames.
+pub const MIN_BUFFER_SIZE: u32 = 16;
+
+/// Largest device buffer size accepted, in frames.
+pub const MAX_BUFFER_SIZE: u32 = 16384;
+
+/// Regex pattern to match durations such as `90`, `90s`, `2m`, `1m30s` or `1h`.
+/// - `(?:(\d+)h)?` Optionally matches a whole number of hours.
+/// - `(?:(\d+)m)?` Optionally matches a whole number of minutes.
+/// - `(?:(\d+(?:\.\d+)?)s?)?` Optionally matches seconds, which may be fractional. The `s` unit is optional.
+///
+/// All parts are optional, so an empty match must be rejected separately.
+pub const DURATION_REGEX: &str = r"^(?:(\d+)h)?(?:(\d+)m)?(?:(\d+(?:\.\d+)?)s?)?$";
+
 /// Regex pattern to match chord input strings.
 /// - `[A-Za-z0-9_+\-#]{1,10}` Matches 1 to 10 characters for the ID. The allowed characters are letters (case-insensitive), digits, underscore, plus, minus, and hash.
 /// - `\(` Matches the literal opening parenthesis.
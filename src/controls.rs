@@ -0,0 +1,272 @@
+use std::{
+    io::{self, IsTerminal, Stderr, Write},
+    time::Duration,
+};
+
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute,
+    style::Print,
+    terminal::{self, ClearType},
+};
+
+use crate::{
+    constants::{MAX_BPM, MIN_BPM},
+    engine::{Control, Controller},
+};
+
+/// BPM change for the up and down arrow keys.
+const FINE_STEP: i32 = 1;
+
+/// BPM change for the left and right arrow keys.
+const COARSE_STEP: i32 = 5;
+
+/// Volume change in percent for the + and - keys.
+const VOLUME_STEP: i32 = 10;
+
+/// How long to wait for a key press before checking whether the tempo changed on its own.
+const REFRESH_INTERVAL: Duration = Duration::from_millis(50);
+
+/// What controls the tempo while playing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Tempo {
+    /// The player, with the arrow keys.
+    Keys,
+    /// A ramp moving the tempo continuously.
+    Ramp,
+    /// The speed trainer, stepping the tempo every few beats.
+    Steps,
+    /// A metric modulation, switching between the two given tempos every few bars.
+    Modulation(f64, f64),
+}
+
+/// An action requested by a key press.
+#[derive(Debug, PartialEq)]
+enum Action {
+    /// Change the tempo by the given number of BPM.
+    ChangeBpm(i32),
+    /// Change the volume by the given percentage.
+    ChangeVolume(i32),
+    /// Mute or unmute the output.
+    ToggleMute,
+    /// Stop the metronome.
+    Quit,
+    /// The key has no meaning.
+    Ignore,
+}
+
+/// Keeps the terminal in raw mode while alive and restores it when dropped, even on early return.
+struct RawMode;
+
+impl RawMode {
+    fn enable() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        Ok(RawMode)
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Runs the interactive controls until the user quits, redrawing the current state in place.
+///
+/// Everything is drawn on stderr so stdout stays free for audio output.
+/// Without a terminal there is nothing to control, so this blocks until stdin is closed instead.
+///
+/// # Arguments
+///
+/// * `controller` - Sends changes to the engine on the audio thread.
+/// * `tempo` - What controls the tempo. Tempo keys are disabled unless it is the keys.
+pub fn run(controller: &mut Controller, tempo: Tempo) -> io::Result<()> {
+    let keys_set_tempo = tempo == Tempo::Keys;
+
+    if !io::stdin().is_terminal() {
+        eprintln!("Playing until stdin is closed.");
+        let reader = std::thread::spawn(|| io::copy(&mut io::stdin(), &mut io::sink()));
+        while !reader.is_finished() && !controller.finished() {
+            std::thread::sleep(REFRESH_INTERVAL);
+        }
+        // The reader is left blocked on stdin if the engine finished first. It ends with the process.
+        if reader.is_finished() {
+            if let Ok(copied) = reader.join() {
+                copied?;
+            }
+        }
+        return Ok(());
+    }
+
+    if let Tempo::Modulation(from, to) = tempo {
+        eprintln!(
+            "Modulating between {} and {} BPM.",
+            format_bpm(from),
+            format_bpm(to)
+        );
+    }
+    if !keys_set_tempo {
+        eprintln!("Press m to mute, +/- to change the volume, and q or Esc to stop the metronome.");
+    } else {
+        eprintln!("Press ↑/↓ to change the BPM by {FINE_STEP}, ←/→ to change it by {COARSE_STEP}, m to mute, +/- to change the volume, and q or Esc to stop the metronome.");
+    }
+
+    let _raw_mode = RawMode::enable()?;
+    let mut stderr = io::stderr();
+
+    // Requested values are tracked here, since the engine only picks them up on its next buffer.
+    let mut target_bpm = controller.bpm();
+    let mut volume: i32 = 100;
+    let mut muted = false;
+    let mut displayed = None;
+
+    // The engine finishes on its own at the end of a ramp that stops.
+    while !controller.finished() {
+        // Redraw whenever the displayed state changes, whether from a key press, a ramp, a step or a new key.
+        let key = controller.key().map(str::to_string);
+        let state = (format_bpm(controller.bpm()), volume, muted, key);
+        if displayed.as_ref() != Some(&state) {
+            // Each step or modulation is reported on its own line, leaving a log of the session.
+            if let Some((previous_bpm, _, _, _)) = &displayed {
+                let reported = matches!(tempo, Tempo::Steps | Tempo::Modulation(..));
+                if reported && *previous_bpm != state.0 {
+                    report_step(&mut stderr, previous_bpm, &state.0)?;
+                }
+            }
+            draw_state(&mut stderr, &state)?;
+            displayed = Some(state);
+        }
+
+        if !event::poll(REFRESH_INTERVAL)? {
+            continue;
+        }
+
+        if let Event::Key(key) = event::read()? {
+            match key_action(key) {
+                Action::Quit => break,
+                Action::ChangeBpm(step) if keys_set_tempo => {
+                    target_bpm = step_bpm(target_bpm, step);
+                    controller.send(Control::Bpm(target_bpm));
+                }
+                Action::ChangeVolume(step) => {
+                    volume = (volume + step).clamp(0, 100);
+                    controller.send(Control::Volume(volume as f32 / 100.0));
+                }
+                Action::ToggleMute => {
+                    muted = !muted;
+                    controller.send(Control::Muted(muted));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Raw mode does not translate newlines, so return the cursor explicitly.
+    execute!(stderr, Print("\r\n"))?;
+    stderr.flush()
+}
+
+/// Maps a key press to the action it requests.
+fn key_action(key: KeyEvent) -> Action {
+    // Some platforms also report key releases and repeats. Only presses should count.
+    if key.kind != KeyEventKind::Press {
+        return Action::Ignore;
+    }
+
+    match key.code {
+        KeyCode::Up => Action::ChangeBpm(FINE_STEP),
+        KeyCode::Down => Action::ChangeBpm(-FINE_STEP),
+        KeyCode::Right => Action::ChangeBpm(COARSE_STEP),
+        KeyCode::Left => Action::ChangeBpm(-COARSE_STEP),
+        KeyCode::Char('+') | KeyCode::Char('=') => Action::ChangeVolume(VOLUME_STEP),
+        KeyCode::Char('-') => Action::ChangeVolume(-VOLUME_STEP),
+        KeyCode::Char('m') => Action::ToggleMute,
+        KeyCode::Char('q') | KeyCode::Esc => Action::Quit,
+        // Raw mode swallows the interrupt signal, so handle Ctrl+C ourselves.
+        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => Action::Quit,
+        _ => Action::Ignore,
+    }
+}
+
+/// Applies a BPM step, keeping the result within the supported tempo range.
+fn step_bpm(bpm: f64, step: i32) -> f64 {
+    (bpm + step as f64).clamp(MIN_BPM, MAX_BPM)
+}
+
+/// Formats a tempo to one decimal place, leaving off the decimal for whole numbers.
+fn format_bpm(bpm: f64) -> String {
+    let rounded = (bpm * 10.0).round() / 10.0;
+    if rounded.fract() == 0.0 {
+        format!("{rounded:.0}")
+    } else {
+        format!("{rounded:.1}")
+    }
+}
+
+/// Overwrites the current terminal line with the given BPM, volume, mute state and key.
+fn draw_state(
+    stderr: &mut Stderr,
+    (bpm, volume, muted, key): &(String, i32, bool, Option<String>),
+) -> io::Result<()> {
+    let muted = if *muted { "  [muted]" } else { "" };
+    let key = key
+        .as_ref()
+        .map_or(String::new(), |key| format!("  Key: {key}"));
+    execute!(
+        stderr,
+        cursor::MoveToColumn(0),
+        terminal::Clear(ClearType::CurrentLine),
+        Print(format!("BPM: {bpm}  Volume: {volume}%{muted}{key}"))
+    )
+}
+
+/// Replaces the current terminal line with a note of a tempo change and moves to the next line.
+fn report_step(stderr: &mut Stderr, from: &str, to: &str) -> io::Result<()> {
+    execute!(
+        stderr,
+        cursor::MoveToColumn(0),
+        terminal::Clear(ClearType::CurrentLine),
+        Print(format!("Tempo changed from {from} to {to} BPM\r\n"))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(KeyCode::Up, Action::ChangeBpm(1))]
+    #[case(KeyCode::Down, Action::ChangeBpm(-1))]
+    #[case(KeyCode::Right, Action::ChangeBpm(5))]
+    #[case(KeyCode::Left, Action::ChangeBpm(-5))]
+    #[case(KeyCode::Char('+'), Action::ChangeVolume(10))]
+    #[case(KeyCode::Char('-'), Action::ChangeVolume(-10))]
+    #[case(KeyCode::Char('m'), Action::ToggleMute)]
+    #[case(KeyCode::Char('q'), Action::Quit)]
+    #[case(KeyCode::Esc, Action::Quit)]
+    #[case(KeyCode::Char('x'), Action::Ignore)]
+    fn keys_map_to_actions(#[case] code: KeyCode, #[case] expected: Action) {
+        assert_eq!(key_action(KeyEvent::from(code)), expected);
+    }
+
+    #[rstest]
+    #[case(120.0, 5, 125.0)]
+    #[case(120.0, -1, 119.0)]
+    #[case(92.5, 1, 93.5)]
+    #[case(498.0, 5, 500.0)]
+    #[case(12.0, -5, 10.0)]
+    fn step_bpm_stays_in_range(#[case] bpm: f64, #[case] step: i32, #[case] expected: f64) {
+        assert_eq!(step_bpm(bpm, step), expected);
+    }
+
+    #[rstest]
+    #[case(120.0, "120")]
+    #[case(92.5, "92.5")]
+    #[case(92.54, "92.5")]
+    #[case(119.97, "120")]
+    fn format_bpm_drops_whole_decimals(#[case] bpm: f64, #[case] expected: &str) {
+        assert_eq!(format_bpm(bpm), expected);
+    }
+}
@@ -0,0 +1,149 @@
+use rand::Rng;
+
+use crate::config::{AppConfig, BeatRange};
+
+/// Plays a number of beats and then drops a number of beats, over and over.
+///
+/// Each part of the cycle is given as a range, and a new length is picked from it every time the
+/// part starts so the gaps never settle into something predictable. With groups, the cycle counts
+/// whole groups instead of beats.
+#[derive(Clone, Debug)]
+pub struct DropCycle {
+    /// Lowest and highest number of units to play.
+    on: BeatRange,
+    /// Lowest and highest number of units to drop.
+    off: BeatRange,
+    /// Length in beats of each unit the cycle counts, repeating.
+    groups: Vec<u8>,
+    /// Whether the current part of the cycle plays.
+    playing: bool,
+    /// Units left in the current part of the cycle.
+    units_left: u8,
+    /// Index of the current unit within the groups.
+    group: usize,
+    /// Beats left in the current unit.
+    beats_left: u8,
+}
+
+impl DropCycle {
+    /// Creates a cycle from the on and off ranges, counting the given groups, or single beats if empty.
+    pub fn new(on: BeatRange, off: BeatRange, groups: &[u8]) -> Self {
+        DropCycle {
+            on,
+            off,
+            groups: if groups.is_empty() {
+                vec![1]
+            } else {
+                groups.to_vec()
+            },
+            // Starts in the dropped part with nothing left, so the first beat begins a playing part.
+            playing: false,
+            units_left: 0,
+            group: 0,
+            beats_left: 0,
+        }
+    }
+
+    /// Builds the cycle from the app config, if drop-beats was given.
+    pub fn from_config(config: &AppConfig) -> Option<Self> {
+        config
+            .drop_beats
+            .map(|(on, off)| DropCycle::new(on, off, config.groups.as_deref().unwrap_or(&[])))
+    }
+
+    /// Moves on to the next beat, picking the length of each part of the cycle as it starts.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the beat plays, `false` if it is dropped.
+    pub fn next_beat(&mut self, rng: &mut impl Rng) -> bool {
+        if self.beats_left == 0 {
+            if self.units_left == 0 {
+                self.playing = !self.playing;
+                let (low, high) = if self.playing { self.on } else { self.off };
+                self.units_left = if low == high {
+                    low
+                } else {
+                    rng.random_range(low..=high)
+                };
+            }
+            self.units_left -= 1;
+            self.beats_left = self.groups[self.group];
+            self.group = (self.group + 1) % self.groups.len();
+        }
+        self.beats_left -= 1;
+        self.playing
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+    use rstest::rstest;
+
+    /// Lists the next beats of a cycle as characters: x = played, . = dropped.
+    fn cycle_steps(cycle: &mut DropCycle, beats: usize) -> String {
+        let mut rng = StdRng::seed_from_u64(7);
+        (0..beats)
+            .map(|_| if cycle.next_beat(&mut rng) { 'x' } else { '.' })
+            .collect()
+    }
+
+    #[rstest]
+    #[case((2, 2), (1, 1), vec![], "xx.xx.xx.")]
+    #[case((1, 1), (1, 1), vec![2, 2, 3], "xx..xxx..xx...")]
+    #[case((2, 2), (1, 1), vec![3, 2], "xxxxx...xxxxx..")]
+    fn fixed_lengths_repeat(
+        #[case] on: (u8, u8),
+        #[case] off: (u8, u8),
+        #[case] groups: Vec<u8>,
+        #[case] expected: &str,
+    ) {
+        let mut cycle = DropCycle::new(on, off, &groups);
+        assert_eq!(cycle_steps(&mut cycle, expected.len()), expected);
+    }
+
+    #[rstest]
+    fn ranged_lengths_stay_in_range() {
+        let mut cycle = DropCycle::new((2, 4), (4, 12), &[]);
+        let steps = cycle_steps(&mut cycle, 1000);
+
+        // Every run but the last, which may be cut short, must fit its range.
+        let played: Vec<usize> = steps
+            .split('.')
+            .filter(|run| !run.is_empty())
+            .map(str::len)
+            .collect();
+        let dropped: Vec<usize> = steps
+            .split('x')
+            .filter(|run| !run.is_empty())
+            .map(str::len)
+            .collect();
+        assert!(played[..played.len() - 1]
+            .iter()
+            .all(|len| (2..=4).contains(len)));
+        assert!(dropped[..dropped.len() - 1]
+            .iter()
+            .all(|len| (4..=12).contains(len)));
+    }
+
+    #[rstest]
+    fn ranged_lengths_vary() {
+        let mut cycle = DropCycle::new((2, 4), (4, 12), &[]);
+        let steps = cycle_steps(&mut cycle, 1000);
+        let dropped: std::collections::HashSet<usize> = steps
+            .split('x')
+            .filter(|run| !run.is_empty())
+            .map(str::len)
+            .collect();
+        assert!(dropped.len() > 1);
+    }
+
+    #[rstest]
+    fn same_seed_repeats_the_cycle() {
+        let mut first = DropCycle::new((2, 4), (4, 12), &[]);
+        let mut second = DropCycle::new((2, 4), (4, 12), &[]);
+        assert_eq!(cycle_steps(&mut first, 200), cycle_steps(&mut second, 200));
+    }
+}
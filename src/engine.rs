@@ -0,0 +1,339 @@
+use std::sync::{
+    atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+    Arc,
+};
+
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use rtrb::{Consumer, Producer, RingBuffer};
+
+use crate::{
+    cli::{RampShape, TransposeCycle},
+    clock::{self, BeatClock},
+    config::AppConfig,
+    drop_cycle::DropCycle,
+    modulation::Modulation,
+    ramp::Ramp,
+    swing::Swing,
+    synth::Synth,
+    trainer::StepTrainer,
+};
+
+/// Number of control messages that can wait for the audio thread before new ones are rejected.
+const CONTROL_QUEUE_SIZE: usize = 64;
+
+/// Messages sent to the engine while it runs on the audio thread.
+///
+/// The pattern is fixed once the engine starts, as building new voices on the audio thread
+/// would allocate. Playing a different pattern means starting a new engine.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Control {
+    /// Change the tempo, in beats per minute.
+    Bpm(f64),
+    /// Silence or restore the output without stopping the beat.
+    Muted(bool),
+    /// Scale the output by the given gain, where 1.0 is full volume.
+    Volume(f32),
+}
+
+/// Sends control messages to an engine from another thread.
+pub struct Controller {
+    producer: Producer<Control>,
+    /// Bits of the tempo most recently played by the engine
+    bpm: Arc<AtomicU64>,
+    /// Set once the engine has nothing left to play
+    finished: Arc<AtomicBool>,
+    /// Names of the keys a transpose cycle moves through
+    keys: Vec<String>,
+    /// Index of the key the engine is currently playing in
+    key: Arc<AtomicUsize>,
+}
+
+impl Controller {
+    /// Queues a message for the engine without blocking.
+    ///
+    /// # Returns
+    ///
+    /// `false` if the queue is full and the message was dropped.
+    pub fn send(&mut self, control: Control) -> bool {
+        self.producer.push(control).is_ok()
+    }
+
+    /// The tempo the engine is currently playing, including changes made by a ramp.
+    pub fn bpm(&self) -> f64 {
+        f64::from_bits(self.bpm.load(Ordering::Relaxed))
+    }
+
+    /// Whether the engine has stopped playing on its own, such as at the end of a ramp that stops.
+    pub fn finished(&self) -> bool {
+        self.finished.load(Ordering::Relaxed)
+    }
+
+    /// The name of the key the progression is playing in, if it moves through a transpose cycle.
+    pub fn key(&self) -> Option<&str> {
+        self.keys
+            .get(self.key.load(Ordering::Relaxed))
+            .map(String::as_str)
+    }
+}
+
+/// Generates metronome samples from a synth, scheduling each beat at the current tempo.
+///
+/// Shared by the live output and offline rendering so both play the same pattern.
+/// The engine owns everything it touches, so filling a buffer never blocks or allocates.
+pub struct Engine {
+    synth: Synth,
+    controls: Consumer<Control>,
+    /// Current tempo in beats per minute
+    bpm: f64,
+    /// Published tempo for the controller, stored as bits since there is no atomic f64
+    shared_bpm: Arc<AtomicU64>,
+    /// Published once a ramp that stops has reached its target and no more beats will play
+    finished: Arc<AtomicBool>,
+    /// Published index of the key a transpose cycle is playing in
+    shared_key: Arc<AtomicUsize>,
+    /// Output sample rate in Hz
+    sample_rate: f64,
+    muted: bool,
+    volume: f32,
+    /// Chance of dropping each beat
+    drop_rate: f64,
+    /// Plays and drops runs of beats, if set
+    drop_cycle: Option<DropCycle>,
+    /// Moves the tempo over time, if set
+    ramp: Option<Ramp>,
+    /// Steps the tempo every few beats, if set
+    trainer: Option<StepTrainer>,
+    /// Switches between two related tempos every few bars, if set
+    modulation: Option<Modulation>,
+    /// Lengthens the first of each pair of steps, if set
+    swing: Option<Swing>,
+    /// Order the synth's keys play in during the current round of a transpose cycle. Empty without a cycle.
+    key_order: Vec<usize>,
+    /// Whether the key order is reshuffled for each round
+    shuffle_keys: bool,
+    /// Decides which beats are dropped, how long each drop-beats run lasts and the order of random keys.
+    /// Seeded from the config when given so the pattern repeats.
+    rng: StdRng,
+    /// Index of the current beat within the pattern
+    beat: usize,
+    /// Number of beats completed since playback started
+    beat_count: u64,
+    /// Index of the next step within the current beat
+    step: u8,
+    /// Number of steps the current beat is split into
+    steps: u8,
+    /// Whether the current beat was dropped by the drop-beats cycle or at random, silencing its subdivisions too
+    beat_dropped: bool,
+    /// Schedules each beat at its exact sample
+    clock: BeatClock,
+}
+
+impl Engine {
+    /// Creates an engine that plays the given synth, along with a controller for adjusting it while it runs.
+    pub fn new(mut synth: Synth, sample_rate: f64, config: &AppConfig) -> (Self, Controller) {
+        synth.set_sample_rate(sample_rate);
+
+        // Ensure we can drop beats during playback if given
+        let drop_rate = if let Some(rate) = config.drop_rate {
+            rate as f64 / 100.0
+        } else {
+            0.0
+        };
+
+        let (producer, controls) = RingBuffer::new(CONTROL_QUEUE_SIZE);
+        let shared_bpm = Arc::new(AtomicU64::new(config.bpm.to_bits()));
+        let finished = Arc::new(AtomicBool::new(false));
+        let shared_key = Arc::new(AtomicUsize::new(0));
+        let keys = synth.keys().to_vec();
+
+        let mut rng = config
+            .seed
+            .map_or_else(StdRng::from_os_rng, StdRng::seed_from_u64);
+        // A random cycle starts as written, then plays the other keys in a random order.
+        let shuffle_keys = config.transpose_cycle == Some(TransposeCycle::Random);
+        let mut key_order: Vec<usize> = (0..keys.len()).collect();
+        if shuffle_keys {
+            key_order[1..].shuffle(&mut rng);
+        }
+
+        let engine = Engine {
+            synth,
+            controls,
+            bpm: config.bpm,
+            shared_bpm: shared_bpm.clone(),
+            finished: finished.clone(),
+            shared_key: shared_key.clone(),
+            sample_rate,
+            muted: false,
+            volume: 1.0,
+            drop_rate,
+            drop_cycle: DropCycle::from_config(config),
+            ramp: Ramp::from_config(config),
+            trainer: StepTrainer::from_config(config),
+            modulation: Modulation::from_config(config),
+            swing: Swing::from_config(config),
+            key_order,
+            shuffle_keys,
+            rng,
+            beat: 0,
+            beat_count: 0,
+            step: 0,
+            steps: 1,
+            beat_dropped: false,
+            clock: BeatClock::new(),
+        };
+        let controller = Controller {
+            producer,
+            bpm: shared_bpm,
+            finished,
+            keys,
+            key: shared_key,
+        };
+
+        (engine, controller)
+    }
+
+    /// Fills an interleaved buffer with samples. Mono buffers get both sides mixed together.
+    /// Otherwise the first two channels are left and right, and any others get the mix.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The interleaved output buffer.
+    /// * `channels` - The number of channels in the output buffer.
+    pub fn fill(&mut self, data: &mut [f32], channels: usize) {
+        self.apply_controls();
+
+        // Move the tempo along the ramp by the duration of this buffer.
+        if let Some(ramp) = self.ramp.as_mut() {
+            let frames = data.len() / channels;
+            self.bpm = ramp.advance(frames as f64 / self.sample_rate);
+        }
+        self.shared_bpm.store(self.bpm.to_bits(), Ordering::Relaxed);
+
+        let gain = if self.muted { 0.0 } else { self.volume };
+
+        // Process each frame in the output buffer.
+        for frame in data.chunks_mut(channels) {
+            // Start each beat or subdivision on its sample and schedule the next one at the current tempo.
+            if self.clock.beat_due() && !self.finished() {
+                self.play_step();
+            }
+
+            // Keep the synth running while muted so the beat carries on underneath.
+            let (left, right) = self.synth.get_stereo();
+            let (left, right) = (left * gain, right * gain);
+            match frame {
+                [mono] => *mono = (left + right) / 2.0,
+                [left_out, right_out, rest @ ..] => {
+                    *left_out = left;
+                    *right_out = right;
+                    // Any extra channels get the center mix.
+                    rest.fill((left + right) / 2.0);
+                }
+                [] => {}
+            }
+
+            self.clock.tick();
+        }
+    }
+
+    /// Whether the engine has stopped playing beats. Voices already sounding are left to ring out.
+    pub fn finished(&self) -> bool {
+        self.finished.load(Ordering::Relaxed)
+    }
+
+    /// Applies every control message waiting in the queue.
+    fn apply_controls(&mut self) {
+        while let Ok(control) = self.controls.pop() {
+            match control {
+                Control::Bpm(bpm) => self.bpm = bpm,
+                Control::Muted(muted) => self.muted = muted,
+                Control::Volume(volume) => self.volume = volume,
+            }
+        }
+    }
+
+    /// Moves a transpose cycle to its next key at the start of each pass of the progression,
+    /// reshuffling a random cycle at the start of each round of keys.
+    fn change_key(&mut self) {
+        let pass_length = self.synth.pass_length() as u64;
+        if self.key_order.is_empty() || !self.beat_count.is_multiple_of(pass_length) {
+            return;
+        }
+        let pass = (self.beat_count / pass_length) as usize;
+        let position = pass % self.key_order.len();
+        if self.shuffle_keys && position == 0 && pass > 0 {
+            // Shuffling in place keeps the audio thread from allocating. The key just played
+            // is moved off the front so no key plays twice in a row.
+            let last = self.key_order[self.key_order.len() - 1];
+            self.key_order.shuffle(&mut self.rng);
+            if self.key_order[0] == last {
+                let end = self.key_order.len() - 1;
+                self.key_order.swap(0, end);
+            }
+        }
+
+        let key = self.key_order[position];
+        self.synth.set_key(key);
+        self.shared_key.store(key, Ordering::Relaxed);
+    }
+
+    /// Triggers the next step of the pattern and schedules the one after it.
+    ///
+    /// The first step of each beat plays the beat itself. Further steps play pattern steps or subdivision clicks.
+    fn play_step(&mut self) {
+        if self.step == 0 {
+            // A ramp that stops ends on the first beat after it reaches its target.
+            if self
+                .ramp
+                .as_ref()
+                .is_some_and(|ramp| ramp.shape() == RampShape::Stop && ramp.finished())
+            {
+                self.finished.store(true, Ordering::Relaxed);
+                return;
+            }
+
+            self.change_key();
+            self.steps = self.synth.steps_in_beat(self.beat, self.beat_count);
+
+            // The speed trainer and modulations change tempo on the beat, so the whole beat plays at the new tempo.
+            // The clock keeps fractions of a sample, so the new tempo starts exactly where the old beat ends.
+            let tempo_change = self
+                .trainer
+                .as_mut()
+                .and_then(StepTrainer::next_beat)
+                .or_else(|| self.modulation.as_mut().and_then(Modulation::next_beat));
+            if let Some(bpm) = tempo_change {
+                self.bpm = bpm;
+                self.shared_bpm.store(bpm.to_bits(), Ordering::Relaxed);
+            }
+
+            // The drop-beats cycle moves on every beat, even ones dropped at random.
+            let cycle_plays = match self.drop_cycle.as_mut() {
+                Some(cycle) => cycle.next_beat(&mut self.rng),
+                None => true,
+            };
+
+            // Each beat is dropped independently, on top of any rests already in the pattern.
+            self.beat_dropped = !cycle_plays || self.rng.random_bool(self.drop_rate);
+        }
+        if !self.beat_dropped {
+            self.synth.play_step(self.beat, self.step);
+        }
+
+        // Steps split the beat at the current tempo, so subdivisions and swing follow a ramp too.
+        let beat_samples = clock::beat_samples(self.bpm, self.sample_rate);
+        let step_length = match &self.swing {
+            Some(swing) => swing.step_length(self.step, self.steps, self.bpm),
+            None => 1.0 / self.steps as f64,
+        };
+        self.clock.schedule(beat_samples * step_length);
+
+        self.step += 1;
+        if self.step == self.steps {
+            self.step = 0;
+            self.beat = (self.beat + 1) % self.synth.beats_per_sequence();
+            self.beat_count += 1;
+        }
+    }
+}
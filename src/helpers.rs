@@ -1,6 +1,8 @@
 use std::{fmt::Display, str::FromStr};
 
-use crate::constants::NOTE_FREQUENCIES;
+use regex::Regex;
+
+use crate::constants::{DURATION_REGEX, NOTE_FREQUENCIES};
 
 /// Converts a note string (e.g., "C4", "G#3", "Bb5") into its frequency in Hz.
 /// Supports notes from Cb2 to B#5 in standard equal temperament (A4 = 440 Hz).
@@ -79,6 +81,46 @@ where
     }
 }
 
+/// Parses a duration such as `90`, `90s`, `2m`, `1m30s` or `1h` into seconds.
+pub fn parse_duration(val: &str, param_name: &str) -> Result<f64, String> {
+    let duration_re =
+        Regex::new(DURATION_REGEX).map_err(|e| format!("Invalid duration regex: {}", e))?;
+    let invalid = || {
+        format!(
+            "Invalid value for {}: {}. Expected seconds or a duration like 90s, 2m or 1m30s.",
+            param_name, val
+        )
+    };
+
+    let captures = duration_re.captures(val.trim()).ok_or_else(invalid)?;
+    if captures.iter().skip(1).all(|group| group.is_none()) {
+        return Err(invalid());
+    }
+
+    // Each group has already been matched as a number, so parsing can only fail on overflow.
+    let part = |index: usize| -> Result<f64, String> {
+        captures.get(index).map_or(Ok(0.0), |m| {
+            m.as_str().parse::<f64>().map_err(|_| invalid())
+        })
+    };
+
+    Ok(part(1)? * 3600.0 + part(2)? * 60.0 + part(3)?)
+}
+
+/// Returns the least common multiple of two lengths, so patterns of each length can repeat until they line up.
+pub fn lcm(a: usize, b: usize) -> usize {
+    a / gcd(a, b) * b
+}
+
+/// Returns the greatest common divisor of two numbers.
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -117,6 +159,15 @@ mod tests {
         assert_eq!(result, expected);
     }
 
+    #[rstest]
+    #[case(4, 6, 12)]
+    #[case(7, 4, 28)]
+    #[case(3, 3, 3)]
+    #[case(1, 5, 5)]
+    fn test_lcm(#[case] a: usize, #[case] b: usize, #[case] expected: usize) {
+        assert_eq!(lcm(a, b), expected);
+    }
+
     #[rstest]
     #[case(50, 30, 100, "bpm", Ok(50))]
     #[case(30, 30, 100, "bpm", Ok(30))]
@@ -266,4 +317,26 @@ mod tests {
             }
         }
     }
+
+    #[rstest]
+    #[case("90", 90.0)]
+    #[case("90s", 90.0)]
+    #[case("2.5s", 2.5)]
+    #[case("2m", 120.0)]
+    #[case("1m30s", 90.0)]
+    #[case("1h", 3600.0)]
+    #[case("1h2m3", 3723.0)]
+    fn test_parse_duration(#[case] val: &str, #[case] expected: f64) {
+        assert_eq!(parse_duration(val, "duration"), Ok(expected));
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("s")]
+    #[case("2x")]
+    #[case("30s2m")]
+    #[case("-5")]
+    fn test_parse_duration_invalid(#[case] val: &str) {
+        assert!(parse_duration(val, "duration").is_err());
+    }
 }
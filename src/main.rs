@@ -1,25 +1,52 @@
-mod audio;
+mod chord;
 mod cli;
+mod clock;
 mod config;
 mod constants;
+mod controls;
+mod drop_cycle;
+mod engine;
 mod helpers;
 mod metronome;
+mod modulation;
+mod output;
+mod pattern;
+mod ramp;
+mod render;
+mod swing;
 mod synth;
+mod trainer;
+mod voicing;
 
-use cli::CliOptions;
-use config::AppConfig;
+use cli::{Cli, Command};
+use config::{AppConfig, OutputConfig, RenderConfig};
 use metronome::Metronome;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Parse CLI Options
-    let cli_options = CliOptions::parse();
+    let cli = Cli::parse();
 
-    // Convert options into app config
-    let config = AppConfig::from_cli(cli_options)?;
+    match cli.command {
+        // Render to a file instead of playing live
+        Some(Command::Render(options)) => {
+            let config = RenderConfig::from_cli(*options)?;
+            render::render_to_wav(&config)?;
+        }
+        Some(Command::Devices) => output::device::list_devices()?,
+        None => {
+            // Clap requires the metronome options when no subcommand is given
+            let options = cli.options.ok_or("missing metronome options")?;
 
-    let metronome = Metronome::new(&config);
+            // Convert options into app config
+            let config = AppConfig::from_cli(options)?;
+            let output_config = OutputConfig::from_cli(cli.output)?;
 
-    metronome.play(&config)?;
+            let backend = output::from_config(&output_config)?;
+            let metronome = Metronome::new(&config);
+
+            metronome.play(&config, backend)?;
+        }
+    }
 
     Ok(())
 }
@@ -1,53 +1,49 @@
-use std::sync::{
-    atomic::{AtomicU32, AtomicU64},
-    Arc, Mutex,
+use crate::{
+    config::AppConfig,
+    controls,
+    engine::Engine,
+    modulation,
+    output::{Backend, Render},
+    synth,
 };
 
-use cpal::traits::StreamTrait;
-
-use crate::{audio, config::AppConfig, synth};
-
 pub struct Metronome {
-    /// Shared adjustable bpm
-    bpm: Arc<AtomicU32>,
-    /// Shared synth to create sounds
-    synth: Arc<Mutex<synth::Synth>>,
-    /// Shared counter to determine when to reset synth
-    sample_counter: Arc<AtomicU64>,
+    /// Synth to create sounds, handed to the audio thread when playback starts
+    synth: synth::Synth,
 }
 
 impl Metronome {
     pub fn new(config: &AppConfig) -> Self {
-        let bpm = Arc::new(AtomicU32::new(config.bpm));
-        let synth = Arc::new(Mutex::new(synth::Synth::from(config)));
-        let sample_counter = Arc::new(AtomicU64::new(0));
-
-        Metronome {
-            bpm,
-            synth,
-            sample_counter,
-        }
-    }
-
-    /// Sets up the audio stream and runs the metronome continuously.
-    pub fn play(&self, config: &AppConfig) -> Result<(), Box<dyn std::error::Error>> {
-        let stream = audio::initialize_audio_stream(
-            self.bpm.clone(),
-            self.synth.clone(),
-            self.sample_counter.clone(),
-            config,
-        )?;
-        stream.play()?;
+        let synth = synth::Synth::from(config);
 
-        wait_for_user_input();
-
-        Ok(())
+        Metronome { synth }
     }
-}
 
-/// Blocks until the user presses Enter.
-fn wait_for_user_input() {
-    println!("Press Enter to stop the metronome.");
-    let mut input = String::new();
-    std::io::stdin().read_line(&mut input).unwrap();
+    /// Starts the given output backend and runs the metronome until the user quits.
+    pub fn play(
+        self,
+        config: &AppConfig,
+        backend: Box<dyn Backend>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let sample_rate = backend.sample_rate() as f64;
+
+        // The engine moves to the audio thread. It is adjusted through the controller from here on.
+        let (mut engine, mut controller) = Engine::new(self.synth, sample_rate, config);
+        let render: Render = Box::new(move |data, channels| engine.fill(data, channels));
+
+        let output = backend.start(render)?;
+
+        let tempo = if config.ramp.is_some() {
+            controls::Tempo::Ramp
+        } else if config.step.is_some() {
+            controls::Tempo::Steps
+        } else if let Some(ratio) = config.modulate {
+            controls::Tempo::Modulation(config.bpm, modulation::modulated_bpm(config.bpm, ratio))
+        } else {
+            controls::Tempo::Keys
+        };
+        controls::run(&mut controller, tempo)?;
+
+        output.stop()
+    }
 }
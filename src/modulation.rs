@@ -0,0 +1,88 @@
+use crate::config::AppConfig;
+
+/// Switches between the starting tempo and a tempo related to it by an exact ratio, every few bars.
+///
+/// Both tempos are worked out once from the starting tempo, so switching back and forth never drifts.
+#[derive(Clone, Debug)]
+pub struct Modulation {
+    /// The starting tempo and the modulated tempo.
+    tempos: [f64; 2],
+    /// Beats to play at each tempo before switching.
+    every: u32,
+    /// Beats played at the current tempo.
+    beats: u32,
+    /// Whether the modulated tempo is playing.
+    modulated: bool,
+}
+
+impl Modulation {
+    /// Creates a modulation where `old` beats at the starting tempo last as long as `new` beats at the
+    /// modulated tempo.
+    pub fn new(bpm: f64, (old, new): (u8, u8), every: u32) -> Self {
+        Modulation {
+            tempos: [bpm, modulated_bpm(bpm, (old, new))],
+            every,
+            beats: 0,
+            modulated: false,
+        }
+    }
+
+    /// Builds the modulation from the app config, if a ratio was given.
+    pub fn from_config(config: &AppConfig) -> Option<Self> {
+        match (config.modulate, config.every) {
+            (Some(ratio), Some(every)) => Some(Modulation::new(config.bpm, ratio, every)),
+            _ => None,
+        }
+    }
+
+    /// Counts the start of a beat, switching tempo once enough beats have played at the current one.
+    ///
+    /// # Returns
+    ///
+    /// The new tempo if it changed on this beat.
+    pub fn next_beat(&mut self) -> Option<f64> {
+        if self.beats < self.every {
+            self.beats += 1;
+            return None;
+        }
+        self.beats = 1;
+        self.modulated = !self.modulated;
+        Some(self.tempos[self.modulated as usize])
+    }
+}
+
+/// The tempo at which `new` beats take as long as `old` beats at the given tempo.
+///
+/// For example, 3:2 turns a dotted quarter into the new quarter note, and 1:3 turns a quarter-note
+/// triplet into the new half note.
+pub fn modulated_bpm(bpm: f64, (old, new): (u8, u8)) -> f64 {
+    bpm * new as f64 / old as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(120.0, (3, 2), 80.0)]
+    #[case(60.0, (1, 3), 180.0)]
+    #[case(90.0, (2, 3), 135.0)]
+    fn modulated_bpm_keeps_the_pulse(
+        #[case] bpm: f64,
+        #[case] ratio: (u8, u8),
+        #[case] expected: f64,
+    ) {
+        assert_eq!(modulated_bpm(bpm, ratio), expected);
+    }
+
+    #[rstest]
+    fn next_beat_switches_tempo_every_few_beats() {
+        let mut modulation = Modulation::new(120.0, (3, 2), 2);
+        let changes: Vec<Option<f64>> = (0..7).map(|_| modulation.next_beat()).collect();
+        assert_eq!(
+            changes,
+            vec![None, None, Some(80.0), None, Some(120.0), None, Some(80.0)]
+        );
+    }
+}
@@ -0,0 +1,381 @@
+use cpal::{
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+    BufferSize, BuildStreamError, Device, FromSample, Host, SampleFormat, SampleRate, SizedSample,
+    Stream, StreamConfig, SupportedBufferSize,
+};
+use std::error::Error;
+
+use super::{Backend, Output, Render};
+use crate::{config::OutputConfig, constants::DEFAULT_SAMPLE_RATE};
+
+/// Sample formats the stream can be built with, in order of preference.
+const SAMPLE_FORMATS: [SampleFormat; 4] = [
+    SampleFormat::F32,
+    SampleFormat::I32,
+    SampleFormat::I16,
+    SampleFormat::U16,
+];
+
+/// Frames in the conversion buffer when the device chooses its own buffer size. Larger callbacks are
+/// rendered in chunks of this size.
+const DEFAULT_BUFFER_FRAMES: usize = 4096;
+
+/// Plays samples through an audio output device.
+pub struct DeviceBackend {
+    device: Device,
+    stream_config: StreamConfig,
+    sample_format: SampleFormat,
+}
+
+impl DeviceBackend {
+    /// Selects an output device and its stream configuration.
+    ///
+    /// The host and device are the defaults unless named in the config. The requested sample rate
+    /// and buffer size are used if the device supports them, otherwise the nearest supported values.
+    pub fn new(config: &OutputConfig) -> Result<Self, Box<dyn Error>> {
+        let host = get_host(config.host.as_deref())?;
+        let device = get_audio_device(&host, config.device.as_deref())?;
+        let (stream_config, sample_format) =
+            get_stream_config(&device, config.sample_rate, config.buffer_size)?;
+
+        Ok(DeviceBackend {
+            device,
+            stream_config,
+            sample_format,
+        })
+    }
+}
+
+impl Backend for DeviceBackend {
+    fn sample_rate(&self) -> u32 {
+        self.stream_config.sample_rate.0
+    }
+
+    /// Builds an output stream that pulls samples from `render` and starts playing it.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Box<dyn Output>)` - The playing stream.
+    /// * `Err(Box<dyn Error>)` - An error if the stream couldn't be created or started.
+    fn start(self: Box<Self>, render: Render) -> Result<Box<dyn Output>, Box<dyn Error>> {
+        let stream = match self.sample_format {
+            SampleFormat::F32 => build_stream::<f32>(&self.device, &self.stream_config, render),
+            SampleFormat::I32 => build_stream::<i32>(&self.device, &self.stream_config, render),
+            SampleFormat::I16 => build_stream::<i16>(&self.device, &self.stream_config, render),
+            SampleFormat::U16 => build_stream::<u16>(&self.device, &self.stream_config, render),
+            format => return Err(format!("unsupported sample format {:?}", format).into()),
+        }?;
+        stream.play()?;
+
+        Ok(Box::new(DeviceOutput { _stream: stream }))
+    }
+}
+
+/// A playing output stream. Playback stops when the stream is dropped.
+struct DeviceOutput {
+    _stream: Stream,
+}
+
+impl Output for DeviceOutput {
+    fn stop(self: Box<Self>) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+}
+
+/// Builds an output stream in the device's sample format, converting from the f32 samples produced by `render`.
+fn build_stream<T>(
+    device: &Device,
+    stream_config: &StreamConfig,
+    mut render: Render,
+) -> Result<Stream, BuildStreamError>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    let channels = stream_config.channels as usize;
+
+    // Allocate the conversion buffer up front so the callback does not need to allocate.
+    let frames = match stream_config.buffer_size {
+        BufferSize::Fixed(frames) => frames as usize,
+        BufferSize::Default => DEFAULT_BUFFER_FRAMES,
+    };
+    let mut buffer: Vec<f32> = vec![0.0; frames.max(1) * channels];
+
+    device.build_output_stream(
+        stream_config,
+        move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+            // Hosts may ask for more samples than the buffer holds, so render whole frames in chunks
+            // instead of growing it.
+            for chunk in data.chunks_mut(buffer.len()) {
+                let rendered = &mut buffer[..chunk.len()];
+                render(rendered, channels);
+                for (sample_out, &sample) in chunk.iter_mut().zip(rendered.iter()) {
+                    *sample_out = T::from_sample(sample);
+                }
+            }
+        },
+        move |err| eprintln!("Stream error: {}", err),
+        None,
+    )
+}
+
+/// Prints every audio host with its output devices and their supported configurations.
+pub fn list_devices() -> Result<(), Box<dyn Error>> {
+    let default_host_id = cpal::default_host().id();
+
+    for host_id in cpal::available_hosts() {
+        let default_marker = if host_id == default_host_id {
+            " (default)"
+        } else {
+            ""
+        };
+        println!("Host: {}{}", host_id.name(), default_marker);
+
+        let host = match cpal::host_from_id(host_id) {
+            Ok(host) => host,
+            Err(err) => {
+                println!("  Unavailable: {}", err);
+                continue;
+            }
+        };
+
+        let default_device_name = host.default_output_device().and_then(|d| d.name().ok());
+        let devices = match host.output_devices() {
+            Ok(devices) => devices,
+            Err(err) => {
+                println!("  Unavailable: {}", err);
+                continue;
+            }
+        };
+        for (index, device) in devices.enumerate() {
+            let name = match device.name() {
+                Ok(name) => name,
+                Err(err) => {
+                    println!("  {}: Unavailable: {}", index, err);
+                    continue;
+                }
+            };
+            let default_marker = if Some(&name) == default_device_name.as_ref() {
+                " (default)"
+            } else {
+                ""
+            };
+            println!("  {}: {}{}", index, name, default_marker);
+
+            let configs = match device.supported_output_configs() {
+                Ok(configs) => configs,
+                Err(err) => {
+                    println!("      Unavailable: {}", err);
+                    continue;
+                }
+            };
+            for config in configs {
+                let buffer_size = match config.buffer_size() {
+                    SupportedBufferSize::Range { min, max } => format!("{}-{} frames", min, max),
+                    SupportedBufferSize::Unknown => "unknown".to_string(),
+                };
+                println!(
+                    "      {} channels, {}-{} Hz, {:?}, buffer {}",
+                    config.channels(),
+                    config.min_sample_rate().0,
+                    config.max_sample_rate().0,
+                    config.sample_format(),
+                    buffer_size
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Gets the audio host with the given name, or the default host.
+fn get_host(name: Option<&str>) -> Result<Host, Box<dyn Error>> {
+    let Some(name) = name else {
+        return Ok(cpal::default_host());
+    };
+
+    let host_ids = cpal::available_hosts();
+    let names: Vec<String> = host_ids.iter().map(|id| id.name().to_string()).collect();
+    match find_choice(&names, name) {
+        Some(index) => Ok(cpal::host_from_id(host_ids[index])?),
+        None => Err(unknown_choice_error("host", name, &names).into()),
+    }
+}
+
+/// Gets the output device with the given name or index, or the host's default output device.
+fn get_audio_device(host: &Host, selector: Option<&str>) -> Result<Device, Box<dyn Error>> {
+    let Some(selector) = selector else {
+        let device = host
+            .default_output_device()
+            .ok_or("no output device available")?;
+        return Ok(device);
+    };
+
+    let mut devices: Vec<Device> = host.output_devices()?.collect();
+    let names = devices
+        .iter()
+        .map(|device| device.name())
+        .collect::<Result<Vec<String>, _>>()?;
+    match find_choice(&names, selector) {
+        Some(index) => Ok(devices.swap_remove(index)),
+        None => Err(unknown_choice_error("output device", selector, &names).into()),
+    }
+}
+
+/// Finds a choice by its index or by its name, ignoring case.
+fn find_choice(names: &[String], selector: &str) -> Option<usize> {
+    if let Ok(index) = selector.parse::<usize>() {
+        if index < names.len() {
+            return Some(index);
+        }
+    }
+    names
+        .iter()
+        .position(|name| name.eq_ignore_ascii_case(selector))
+}
+
+/// Builds an error message for an unknown selection that lists the valid choices.
+fn unknown_choice_error(kind: &str, selector: &str, names: &[String]) -> String {
+    let choices: Vec<String> = names
+        .iter()
+        .enumerate()
+        .map(|(index, name)| format!("  {}: {}", index, name))
+        .collect();
+    format!(
+        "Unknown {} '{}'. Valid choices are:\n{}",
+        kind,
+        selector,
+        choices.join("\n")
+    )
+}
+
+/// Retrieves the stream configuration and sample format for the given audio device.
+///
+/// # Arguments
+///
+/// * `device` - The output device.
+/// * `sample_rate` - The requested sample rate. Uses the device's default rate if `None`.
+/// * `buffer_size` - The requested buffer size in frames. Lets the device decide if `None`.
+fn get_stream_config(
+    device: &Device,
+    sample_rate: Option<u32>,
+    buffer_size: Option<u32>,
+) -> Result<(StreamConfig, SampleFormat), Box<dyn Error>> {
+    // Aim for the requested rate, otherwise the device's default rather than its maximum,
+    // which can be needlessly high.
+    let wanted_rate = match sample_rate {
+        Some(rate) => rate,
+        None => device
+            .default_output_config()
+            .map(|config| config.sample_rate().0)
+            .unwrap_or(DEFAULT_SAMPLE_RATE),
+    };
+
+    // Prefer configurations that support the rate, then the best sample format.
+    let supported_config = device
+        .supported_output_configs()?
+        .filter_map(|config| {
+            let rank = format_rank(config.sample_format())?;
+            let unsupported_rate =
+                !(config.min_sample_rate().0..=config.max_sample_rate().0).contains(&wanted_rate);
+            Some(((unsupported_rate, rank), config))
+        })
+        .min_by_key(|(key, _)| *key)
+        .map(|(_, config)| config)
+        .ok_or("no supported output configuration with f32, i32, i16 or u16 sample format")?;
+
+    let rate = wanted_rate.clamp(
+        supported_config.min_sample_rate().0,
+        supported_config.max_sample_rate().0,
+    );
+    if sample_rate.is_some_and(|requested| requested != rate) {
+        eprintln!(
+            "Warning: sample rate {} Hz is not supported by this device. Using {} Hz.",
+            wanted_rate, rate
+        );
+    }
+
+    let frames = buffer_size.map(|requested| {
+        let frames = fit_buffer_size(supported_config.buffer_size(), requested);
+        if frames != requested {
+            eprintln!(
+                "Warning: buffer size {} is not supported by this device. Using {}.",
+                requested, frames
+            );
+        }
+        frames
+    });
+
+    let sample_format = supported_config.sample_format();
+    let mut config: StreamConfig = supported_config.with_sample_rate(SampleRate(rate)).config();
+    if let Some(frames) = frames {
+        config.buffer_size = BufferSize::Fixed(frames);
+    }
+
+    Ok((config, sample_format))
+}
+
+/// Ranks a sample format by preference, lowest first. Returns `None` for unsupported formats.
+fn format_rank(format: SampleFormat) -> Option<usize> {
+    SAMPLE_FORMATS.iter().position(|&f| f == format)
+}
+
+/// Clamps a requested buffer size to the range the device supports, if known.
+fn fit_buffer_size(supported: &SupportedBufferSize, requested: u32) -> u32 {
+    match supported {
+        SupportedBufferSize::Range { min, max } => requested.clamp(*min, *max),
+        SupportedBufferSize::Unknown => requested,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn names() -> Vec<String> {
+        vec!["Speakers".to_string(), "In-Ear Monitors".to_string()]
+    }
+
+    #[rstest]
+    #[case("0", Some(0))]
+    #[case("1", Some(1))]
+    #[case("In-Ear Monitors", Some(1))]
+    #[case("speakers", Some(0))]
+    #[case("2", None)]
+    #[case("Headphones", None)]
+    fn find_choice_matches_index_or_name(#[case] selector: &str, #[case] expected: Option<usize>) {
+        assert_eq!(find_choice(&names(), selector), expected);
+    }
+
+    #[rstest]
+    #[case(SampleFormat::F32, Some(0))]
+    #[case(SampleFormat::I16, Some(2))]
+    #[case(SampleFormat::U16, Some(3))]
+    #[case(SampleFormat::F64, None)]
+    fn format_rank_prefers_f32(#[case] format: SampleFormat, #[case] expected: Option<usize>) {
+        assert_eq!(format_rank(format), expected);
+    }
+
+    #[rstest]
+    #[case(SupportedBufferSize::Range { min: 64, max: 4096 }, 256, 256)]
+    #[case(SupportedBufferSize::Range { min: 64, max: 4096 }, 16, 64)]
+    #[case(SupportedBufferSize::Range { min: 64, max: 4096 }, 8192, 4096)]
+    #[case(SupportedBufferSize::Unknown, 128, 128)]
+    fn fit_buffer_size_clamps_to_range(
+        #[case] supported: SupportedBufferSize,
+        #[case] requested: u32,
+        #[case] expected: u32,
+    ) {
+        assert_eq!(fit_buffer_size(&supported, requested), expected);
+    }
+
+    #[rstest]
+    fn unknown_choice_error_lists_choices() {
+        let err = unknown_choice_error("output device", "Headphones", &names());
+        assert_eq!(
+            err,
+            "Unknown output device 'Headphones'. Valid choices are:\n  0: Speakers\n  1: In-Ear Monitors"
+        );
+    }
+}
@@ -0,0 +1,50 @@
+use std::error::Error;
+
+use crate::{cli::OutputKind, config::OutputConfig, constants::DEFAULT_SAMPLE_RATE};
+
+pub mod device;
+pub mod sink;
+
+/// Fills an interleaved buffer with metronome samples for the given number of channels.
+pub type Render = Box<dyn FnMut(&mut [f32], usize) + Send>;
+
+/// Somewhere to send the metronome's samples.
+pub trait Backend {
+    /// The sample rate the backend plays at, in Hz.
+    fn sample_rate(&self) -> u32;
+
+    /// Starts pulling samples from `render` in real time.
+    fn start(self: Box<Self>, render: Render) -> Result<Box<dyn Output>, Box<dyn Error>>;
+}
+
+/// Output that has been started by a backend.
+pub trait Output {
+    /// Stops the output and flushes anything still buffered.
+    fn stop(self: Box<Self>) -> Result<(), Box<dyn Error>>;
+}
+
+/// Creates the backend chosen in the output config.
+pub fn from_config(config: &OutputConfig) -> Result<Box<dyn Backend>, Box<dyn Error>> {
+    let sink_sample_rate = config.sample_rate.unwrap_or(DEFAULT_SAMPLE_RATE);
+    let backend: Box<dyn Backend> = match config.kind {
+        OutputKind::Device => Box::new(device::DeviceBackend::new(config)?),
+        OutputKind::Null => Box::new(sink::SinkBackend::new(
+            Box::new(sink::NullSink),
+            sink_sample_rate,
+        )),
+        OutputKind::File => {
+            // Validation has already ensured the path is set for file output.
+            let path = config.out.as_ref().ok_or("file output requires --out")?;
+            Box::new(sink::SinkBackend::new(
+                Box::new(sink::WavSink::create(path, sink_sample_rate)?),
+                sink_sample_rate,
+            ))
+        }
+        OutputKind::Stdout => Box::new(sink::SinkBackend::new(
+            Box::new(sink::StdoutSink::new()),
+            sink_sample_rate,
+        )),
+    };
+
+    Ok(backend)
+}
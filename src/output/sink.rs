@@ -0,0 +1,169 @@
+use std::{
+    error::Error,
+    fs::File,
+    io::{self, BufWriter, Stdout, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use hound::{SampleFormat, WavSpec, WavWriter};
+
+use super::{Backend, Output, Render};
+
+/// Number of frames generated at a time, similar to a live audio buffer.
+pub const BLOCK_FRAMES: usize = 512;
+
+/// Consumes mono samples without an audio device.
+pub trait Sink: Send {
+    /// Writes a block of mono samples.
+    fn write(&mut self, samples: &[f32]) -> io::Result<()>;
+
+    /// Flushes and closes the sink.
+    fn finish(self: Box<Self>) -> io::Result<()>;
+}
+
+/// Feeds a sink from a background thread, paced to real time so tempo controls behave as they would on a device.
+pub struct SinkBackend {
+    sink: Box<dyn Sink>,
+    sample_rate: u32,
+}
+
+impl SinkBackend {
+    pub fn new(sink: Box<dyn Sink>, sample_rate: u32) -> Self {
+        SinkBackend { sink, sample_rate }
+    }
+}
+
+impl Backend for SinkBackend {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn start(self: Box<Self>, mut render: Render) -> Result<Box<dyn Output>, Box<dyn Error>> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let SinkBackend {
+            mut sink,
+            sample_rate,
+        } = *self;
+
+        let thread = thread::spawn(move || -> io::Result<()> {
+            let mut block = [0.0f32; BLOCK_FRAMES];
+            let started = Instant::now();
+            let mut frames_written: u64 = 0;
+
+            while !thread_stop.load(Ordering::Relaxed) {
+                render(&mut block, 1);
+                sink.write(&block)?;
+                frames_written += BLOCK_FRAMES as u64;
+
+                // Sleep until the samples written so far would have finished playing.
+                let written = Duration::from_secs_f64(frames_written as f64 / sample_rate as f64);
+                if let Some(ahead) = written.checked_sub(started.elapsed()) {
+                    thread::sleep(ahead);
+                }
+            }
+
+            sink.finish()
+        });
+
+        Ok(Box::new(SinkOutput { stop, thread }))
+    }
+}
+
+/// A running sink thread.
+struct SinkOutput {
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<io::Result<()>>,
+}
+
+impl Output for SinkOutput {
+    fn stop(self: Box<Self>) -> Result<(), Box<dyn Error>> {
+        self.stop.store(true, Ordering::Relaxed);
+        self.thread.join().map_err(|_| "output thread panicked")??;
+        Ok(())
+    }
+}
+
+/// Discards all samples. Useful for running without a sound card.
+pub struct NullSink;
+
+impl Sink for NullSink {
+    fn write(&mut self, _samples: &[f32]) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes samples to a mono 16-bit PCM WAV file.
+pub struct WavSink {
+    writer: WavWriter<BufWriter<File>>,
+}
+
+impl WavSink {
+    pub fn create(path: &Path, sample_rate: u32) -> Result<Self, Box<dyn Error>> {
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+        let writer = WavWriter::create(path, spec)?;
+
+        Ok(WavSink { writer })
+    }
+}
+
+impl Sink for WavSink {
+    fn write(&mut self, samples: &[f32]) -> io::Result<()> {
+        for &sample in samples {
+            self.writer
+                .write_sample(to_i16(sample))
+                .map_err(io::Error::other)?;
+        }
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        self.writer.finalize().map_err(io::Error::other)
+    }
+}
+
+/// Writes raw mono signed 16-bit little-endian PCM to stdout, for piping into other programs.
+pub struct StdoutSink {
+    stdout: BufWriter<Stdout>,
+}
+
+impl StdoutSink {
+    pub fn new() -> Self {
+        StdoutSink {
+            stdout: BufWriter::new(io::stdout()),
+        }
+    }
+}
+
+impl Sink for StdoutSink {
+    fn write(&mut self, samples: &[f32]) -> io::Result<()> {
+        for &sample in samples {
+            self.stdout.write_all(&to_i16(sample).to_le_bytes())?;
+        }
+        self.stdout.flush()
+    }
+
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        self.stdout.flush()
+    }
+}
+
+/// Converts a sample in the range [-1.0, 1.0] to a 16-bit integer, clipping anything outside it.
+fn to_i16(sample: f32) -> i16 {
+    (sample.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16
+}
@@ -0,0 +1,160 @@
+/// A single step of a `--pattern` grid.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Step {
+    /// `x` plays a normal click.
+    Hit,
+    /// `X` plays an accented click.
+    Accent,
+    /// `g` plays a quiet ghost click.
+    Ghost,
+    /// `.` is silent.
+    Rest,
+}
+
+/// Parses a rhythm pattern such as `x..x..x.|x.x.x.x.` into bars of steps.
+///
+/// Each character is one step of the grid and bars are separated by `|`. Spaces are ignored,
+/// so long patterns can be grouped for readability.
+///
+/// # Returns
+///
+/// * `Ok(Vec<Vec<Step>>)` - The steps of each bar.
+/// * `Err(String)` - An error pointing at the column of the first problem.
+pub fn parse_pattern(pattern: &str) -> Result<Vec<Vec<Step>>, String> {
+    let mut bars = vec![];
+    let mut bar = vec![];
+    // Column where the current bar starts, for reporting empty bars.
+    let mut bar_start = 0;
+
+    for (column, c) in pattern.chars().enumerate() {
+        let step = match c {
+            'x' => Step::Hit,
+            'X' => Step::Accent,
+            'g' => Step::Ghost,
+            '.' => Step::Rest,
+            ' ' => continue,
+            '|' => {
+                if bar.is_empty() {
+                    return Err(column_error(pattern, bar_start, "empty bar"));
+                }
+                bars.push(std::mem::take(&mut bar));
+                bar_start = column + 1;
+                continue;
+            }
+            other => {
+                return Err(column_error(
+                    pattern,
+                    column,
+                    &format!("unexpected '{}'. Use x, X, g, . or |", other),
+                ))
+            }
+        };
+        bar.push(step);
+    }
+
+    if bar.is_empty() {
+        return Err(column_error(
+            pattern,
+            bar_start.min(pattern.chars().count()),
+            "empty bar",
+        ));
+    }
+    bars.push(bar);
+
+    Ok(bars)
+}
+
+/// Builds an error message that shows the pattern with a caret under the given column.
+///
+/// # Arguments
+///
+/// * `pattern` - The full pattern as given.
+/// * `column` - The zero-based column of the problem.
+/// * `message` - What is wrong at that column.
+pub fn column_error(pattern: &str, column: usize, message: &str) -> String {
+    format!(
+        "Invalid value for pattern at column {}: {}.\n  {}\n  {}^",
+        column + 1,
+        message,
+        pattern,
+        " ".repeat(column)
+    )
+}
+
+/// Finds the zero-based column where each bar of a pattern starts, skipping leading spaces.
+pub fn bar_columns(pattern: &str) -> Vec<usize> {
+    let mut columns = vec![];
+    let mut at_bar_start = true;
+    for (column, c) in pattern.chars().enumerate() {
+        match c {
+            '|' => at_bar_start = true,
+            ' ' => {}
+            _ if at_bar_start => {
+                columns.push(column);
+                at_bar_start = false;
+            }
+            _ => {}
+        }
+    }
+    columns
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    fn parse_pattern_reads_steps_and_bars() {
+        use Step::*;
+        assert_eq!(
+            parse_pattern("x..X|g.x.").unwrap(),
+            vec![vec![Hit, Rest, Rest, Accent], vec![Ghost, Rest, Hit, Rest]]
+        );
+    }
+
+    #[rstest]
+    fn parse_pattern_ignores_spaces() {
+        assert_eq!(
+            parse_pattern("x.x. x.x.").unwrap(),
+            parse_pattern("x.x.x.x.").unwrap()
+        );
+    }
+
+    #[rstest]
+    #[case("x..y", 4, "unexpected 'y'")]
+    #[case("x..x||x...", 6, "empty bar")]
+    #[case("|x...", 1, "empty bar")]
+    #[case("x...|", 6, "empty bar")]
+    #[case("", 1, "empty bar")]
+    fn parse_pattern_points_at_column(
+        #[case] pattern: &str,
+        #[case] column: usize,
+        #[case] message: &str,
+    ) {
+        let err = parse_pattern(pattern).unwrap_err();
+        assert!(
+            err.starts_with(&format!(
+                "Invalid value for pattern at column {}: {}",
+                column, message
+            )),
+            "{}",
+            err
+        );
+    }
+
+    #[rstest]
+    fn column_error_draws_a_caret() {
+        assert_eq!(
+            column_error("x..y", 3, "unexpected 'y'"),
+            "Invalid value for pattern at column 4: unexpected 'y'.\n  x..y\n     ^"
+        );
+    }
+
+    #[rstest]
+    #[case("x..x..x.|x.x.x.x.", vec![0, 9])]
+    #[case(" x.x. | x...", vec![1, 8])]
+    fn bar_columns_finds_bar_starts(#[case] pattern: &str, #[case] expected: Vec<usize>) {
+        assert_eq!(bar_columns(pattern), expected);
+    }
+}
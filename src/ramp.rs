@@ -0,0 +1,227 @@
+use std::f64::consts::PI;
+
+use crate::{cli::RampShape, config::AppConfig};
+
+/// Default BPM change per second when a ramp is set without a change rate.
+const DEFAULT_CHANGE_RATE: f64 = 1.0;
+
+/// Moves the tempo from the starting BPM toward a target BPM, following the chosen shape.
+#[derive(Clone, Debug)]
+pub struct Ramp {
+    /// Starting tempo.
+    start: f64,
+    /// Target tempo.
+    target: f64,
+    /// Lower tempo bound.
+    low: f64,
+    /// Upper tempo bound.
+    high: f64,
+    /// Change in BPM per second, or percent per second for an exponential ramp.
+    rate: f64,
+    shape: RampShape,
+    /// The current, unrounded tempo.
+    current: f64,
+    /// Whether the tempo is currently increasing.
+    rising: bool,
+    /// Seconds since the ramp started.
+    elapsed: f64,
+}
+
+impl Ramp {
+    pub fn new(start: f64, target: f64, change_rate: f64, shape: RampShape) -> Self {
+        Ramp {
+            start,
+            target,
+            low: start.min(target),
+            high: start.max(target),
+            rate: change_rate,
+            shape,
+            current: start,
+            rising: target > start,
+            elapsed: 0.0,
+        }
+    }
+
+    /// Builds a ramp from the app config, if a ramp target was given.
+    pub fn from_config(config: &AppConfig) -> Option<Self> {
+        config.ramp.map(|target| {
+            Ramp::new(
+                config.bpm,
+                target,
+                config.change_rate.unwrap_or(DEFAULT_CHANGE_RATE),
+                config.ramp_shape,
+            )
+        })
+    }
+
+    pub fn shape(&self) -> RampShape {
+        self.shape
+    }
+
+    /// Whether a ramp that runs once has reached its target.
+    pub fn finished(&self) -> bool {
+        matches!(self.shape, RampShape::Hold | RampShape::Stop) && self.current == self.target
+    }
+
+    /// Advances the ramp by the given number of seconds.
+    ///
+    /// # Returns
+    ///
+    /// The tempo after advancing.
+    pub fn advance(&mut self, seconds: f64) -> f64 {
+        self.elapsed += seconds;
+
+        match self.shape {
+            RampShape::Bounce => self.bounce(self.rate * seconds),
+            RampShape::Hold | RampShape::Stop => {
+                let step = self.rate * seconds;
+                self.current = if self.rising {
+                    (self.current + step).min(self.target)
+                } else {
+                    (self.current - step).max(self.target)
+                };
+            }
+            RampShape::Sine => {
+                // Half a period takes as long as a linear ramp at the same rate, so the rate keeps its meaning.
+                let half_period = (self.high - self.low) / self.rate;
+                if half_period > 0.0 {
+                    let phase = PI * self.elapsed / half_period;
+                    self.current =
+                        self.start + (self.target - self.start) * (1.0 - phase.cos()) / 2.0;
+                }
+            }
+            RampShape::Exponential => {
+                // Bounce in log space, so each second multiplies the tempo by the same amount.
+                let (low, high) = (self.low.ln(), self.high.ln());
+                let mut current = self.current.ln();
+                let step = (1.0 + self.rate / 100.0).ln() * seconds;
+                if self.rising {
+                    current += step;
+                    if current >= high {
+                        current = (2.0 * high - current).max(low);
+                        self.rising = false;
+                    }
+                } else {
+                    current -= step;
+                    if current <= low {
+                        current = (2.0 * low - current).min(high);
+                        self.rising = true;
+                    }
+                }
+                self.current = current.exp();
+            }
+        }
+
+        self.current
+    }
+
+    /// Moves the tempo by the given number of BPM, reversing direction at either end.
+    fn bounce(&mut self, step: f64) {
+        if self.rising {
+            self.current += step;
+            if self.current >= self.high {
+                self.current = (2.0 * self.high - self.current).max(self.low);
+                self.rising = false;
+            }
+        } else {
+            self.current -= step;
+            if self.current <= self.low {
+                self.current = (2.0 * self.low - self.current).min(self.high);
+                self.rising = true;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(60.0, 200.0, 5.0, 2.0, 70.0)]
+    #[case(300.0, 100.0, 1.0, 10.0, 290.0)]
+    #[case(100.0, 100.0, 5.0, 3.0, 100.0)]
+    #[case(92.5, 120.0, 2.0, 0.5, 93.5)]
+    #[case(100.0, 110.0, 0.5, 4.0, 102.0)]
+    fn advance_moves_toward_target(
+        #[case] start: f64,
+        #[case] target: f64,
+        #[case] rate: f64,
+        #[case] seconds: f64,
+        #[case] expected: f64,
+    ) {
+        let mut ramp = Ramp::new(start, target, rate, RampShape::Bounce);
+        assert_eq!(ramp.advance(seconds), expected);
+    }
+
+    #[rstest]
+    fn advance_reverses_at_the_target() {
+        let mut ramp = Ramp::new(100.0, 110.0, 5.0, RampShape::Bounce);
+        assert_eq!(ramp.advance(3.0), 105.0);
+        assert_eq!(ramp.advance(1.0), 100.0);
+    }
+
+    #[rstest]
+    fn advance_reverses_at_the_start() {
+        let mut ramp = Ramp::new(100.0, 110.0, 5.0, RampShape::Bounce);
+        ramp.advance(2.0);
+        assert_eq!(ramp.advance(2.0), 100.0);
+        assert_eq!(ramp.advance(1.0), 105.0);
+    }
+
+    #[rstest]
+    #[case(RampShape::Hold)]
+    #[case(RampShape::Stop)]
+    fn advance_runs_once(#[case] shape: RampShape) {
+        let mut ramp = Ramp::new(100.0, 110.0, 5.0, shape);
+        assert_eq!(ramp.advance(1.0), 105.0);
+        assert!(!ramp.finished());
+        assert_eq!(ramp.advance(3.0), 110.0);
+        assert!(ramp.finished());
+        assert_eq!(ramp.advance(1.0), 110.0);
+    }
+
+    #[rstest]
+    fn bounce_never_finishes() {
+        let mut ramp = Ramp::new(100.0, 110.0, 5.0, RampShape::Bounce);
+        ramp.advance(2.0);
+        assert!(!ramp.finished());
+    }
+
+    #[rstest]
+    #[case(1.0, 105.0)]
+    #[case(2.0, 110.0)]
+    #[case(3.0, 105.0)]
+    #[case(4.0, 100.0)]
+    fn sine_oscillates_between_start_and_target(#[case] seconds: f64, #[case] expected: f64) {
+        let mut ramp = Ramp::new(100.0, 110.0, 5.0, RampShape::Sine);
+        assert!((ramp.advance(seconds) - expected).abs() < 1e-9);
+    }
+
+    #[rstest]
+    fn sine_eases_in() {
+        let mut ramp = Ramp::new(100.0, 110.0, 5.0, RampShape::Sine);
+        assert!(ramp.advance(0.5) < 102.5);
+    }
+
+    #[rstest]
+    fn exponential_changes_by_a_percentage() {
+        let mut ramp = Ramp::new(100.0, 200.0, 10.0, RampShape::Exponential);
+        assert!((ramp.advance(1.0) - 110.0).abs() < 1e-9);
+        assert!((ramp.advance(1.0) - 121.0).abs() < 1e-9);
+    }
+
+    #[rstest]
+    fn exponential_reverses_at_the_target() {
+        let mut ramp = Ramp::new(100.0, 110.0, 10.0, RampShape::Exponential);
+        assert!((ramp.advance(1.0) - 110.0).abs() < 1e-9);
+        assert!((ramp.advance(1.0) - 100.0).abs() < 1e-9);
+    }
+
+    #[rstest]
+    fn exponential_falls_toward_a_lower_target() {
+        let mut ramp = Ramp::new(121.0, 100.0, 10.0, RampShape::Exponential);
+        assert!((ramp.advance(1.0) - 110.0).abs() < 1e-9);
+    }
+}
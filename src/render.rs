@@ -0,0 +1,40 @@
+use std::error::Error;
+
+use crate::{
+    config::RenderConfig,
+    engine::Engine,
+    output::sink::{Sink, WavSink, BLOCK_FRAMES},
+    synth::Synth,
+};
+
+/// Renders the metronome to a mono 16-bit PCM WAV file as fast as possible.
+///
+/// Samples are generated by the same engine as live playback, so the file matches what would be heard.
+pub fn render_to_wav(config: &RenderConfig) -> Result<(), Box<dyn Error>> {
+    let sample_rate = config.sample_rate as f64;
+    let total_frames = (config.duration * sample_rate).round() as usize;
+
+    // Nothing adjusts the engine while rendering, so the controller is not needed.
+    let (mut engine, _controller) = Engine::new(Synth::from(&config.app), sample_rate, &config.app);
+
+    let mut sink = Box::new(WavSink::create(&config.out, config.sample_rate)?);
+
+    let mut block = [0.0f32; BLOCK_FRAMES];
+    let mut remaining = total_frames;
+    // A ramp that stops can end the recording early.
+    while remaining > 0 && !engine.finished() {
+        let frames = remaining.min(BLOCK_FRAMES);
+        engine.fill(&mut block[..frames], 1);
+        sink.write(&block[..frames])?;
+        remaining -= frames;
+    }
+
+    sink.finish()?;
+    println!(
+        "Wrote {:.1} seconds to {}",
+        (total_frames - remaining) as f64 / sample_rate,
+        config.out.display()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,111 @@
+use crate::config::AppConfig;
+
+/// Tempo at or below which a swing curve plays the full swing ratio.
+const FULL_SWING_BPM: f64 = 120.0;
+
+/// Tempo at or above which a swing curve plays straight.
+const STRAIGHT_BPM: f64 = 300.0;
+
+/// Shifts time between pairs of steps within a beat, lengthening the first and delaying the second.
+#[derive(Clone, Debug)]
+pub struct Swing {
+    /// Share of each pair of steps given to the first step, from 0.5 (straight) up.
+    ratio: f64,
+    /// Whether the swing straightens out as the tempo rises.
+    follows_tempo: bool,
+}
+
+impl Swing {
+    pub fn new(ratio: f64, follows_tempo: bool) -> Self {
+        Swing {
+            ratio,
+            follows_tempo,
+        }
+    }
+
+    /// Builds the swing from the app config, if swing was given.
+    pub fn from_config(config: &AppConfig) -> Option<Self> {
+        config
+            .swing
+            .map(|ratio| Swing::new(ratio, config.swing_curve))
+    }
+
+    /// The swing ratio at the given tempo.
+    ///
+    /// With a swing curve, the ratio eases from its full value at slow tempos to straight at fast ones,
+    /// the way players tighten their swing as the tempo goes up.
+    pub fn ratio_at(&self, bpm: f64) -> f64 {
+        if !self.follows_tempo {
+            return self.ratio;
+        }
+        let amount = ((STRAIGHT_BPM - bpm) / (STRAIGHT_BPM - FULL_SWING_BPM)).clamp(0.0, 1.0);
+        0.5 + (self.ratio - 0.5) * amount
+    }
+
+    /// The length of a step as a fraction of its beat.
+    ///
+    /// Steps are swung in pairs, so beats with an odd number of steps, such as triplets, stay straight.
+    ///
+    /// # Arguments
+    ///
+    /// * `step` - The index of the step within the beat.
+    /// * `steps` - The number of steps in the beat.
+    /// * `bpm` - The current tempo.
+    pub fn step_length(&self, step: u8, steps: u8, bpm: f64) -> f64 {
+        let straight = 1.0 / steps as f64;
+        if !steps.is_multiple_of(2) {
+            return straight;
+        }
+
+        let ratio = self.ratio_at(bpm);
+        if step.is_multiple_of(2) {
+            straight * 2.0 * ratio
+        } else {
+            straight * 2.0 * (1.0 - ratio)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(0, 2, 0.6)]
+    #[case(1, 2, 0.4)]
+    #[case(0, 4, 0.3)]
+    #[case(3, 4, 0.2)]
+    #[case(0, 3, 1.0 / 3.0)]
+    #[case(0, 1, 1.0)]
+    fn step_length_swings_pairs(#[case] step: u8, #[case] steps: u8, #[case] expected: f64) {
+        let swing = Swing::new(0.6, false);
+        assert!((swing.step_length(step, steps, 120.0) - expected).abs() < 1e-9);
+    }
+
+    #[rstest]
+    #[case(2)]
+    #[case(4)]
+    #[case(6)]
+    fn step_lengths_fill_the_beat(#[case] steps: u8) {
+        let swing = Swing::new(2.0 / 3.0, true);
+        let total: f64 = (0..steps)
+            .map(|step| swing.step_length(step, steps, 180.0))
+            .sum();
+        assert!((total - 1.0).abs() < 1e-9);
+    }
+
+    #[rstest]
+    #[case(false, 280.0, 0.7)]
+    #[case(true, 100.0, 0.7)]
+    #[case(true, 210.0, 0.6)]
+    #[case(true, 320.0, 0.5)]
+    fn ratio_at_follows_the_curve(
+        #[case] follows_tempo: bool,
+        #[case] bpm: f64,
+        #[case] expected: f64,
+    ) {
+        let swing = Swing::new(0.7, follows_tempo);
+        assert!((swing.ratio_at(bpm) - expected).abs() < 1e-9);
+    }
+}
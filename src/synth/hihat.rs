@@ -1,17 +1,54 @@
 use fundsp::prelude::*;
 
+use super::{Beat, Emphasis, Voice};
+
+/// Hi-hat burst length in seconds.
+const BURST_DURATION: f32 = 0.03;
+
+/// Level of the main hi-hat click.
+const CLICK_GAIN: f32 = 0.5;
+
+/// Bandpass center frequency of the main hi-hat click in Hz.
+const CLICK_CENTER: f32 = 1000.0;
+
+/// Level of accented hi-hat clicks.
+const ACCENT_GAIN: f32 = 0.8;
+
+/// Bandpass center frequency of accented hi-hat clicks in Hz.
+const ACCENT_CENTER: f32 = 1600.0;
+
+/// Bandpass center frequency of clicks starting a group in Hz, darker than the main click.
+const GROUP_CENTER: f32 = 650.0;
+
+/// Level of ghost clicks, barely there.
+const GHOST_GAIN: f32 = 0.15;
+
+/// Level of subdivision clicks, quieter so the beat stays clear.
+const SUBDIVISION_GAIN: f32 = 0.2;
+
+/// Bandpass center frequency of subdivision clicks in Hz, brighter to set them apart from the beat.
+const SUBDIVISION_CENTER: f32 = 2500.0;
+
 /// Constructs a hi‑hat synth that produces a single 50ms burst with a sine-shaped attack.
 ///
 /// Call `reset()` on the returned unit to retrigger the burst.
 pub fn hihat_synth() -> Box<dyn AudioUnit> {
+    click_synth(CLICK_GAIN, CLICK_CENTER)
+}
+
+/// Constructs a hi-hat style burst with the given level and bandpass center frequency.
+///
+/// # Arguments
+///
+/// * `gain` - The level of the noise before filtering.
+/// * `bp_center` - The bandpass center frequency in Hz. Higher values sound brighter.
+pub fn click_synth(gain: f32, bp_center: f32) -> Box<dyn AudioUnit> {
     // Burst length in seconds.
-    let burst_duration = 0.03;
+    let burst_duration = BURST_DURATION;
     // Short attack duration (in seconds).
     let attack_time = 0.001;
     // Controls exponential decay (higher means faster decay) for the remainder.
     let decay_factor = 150.0;
-    // Bandpass center frequency in Hz.
-    let bp_center = 1000.0;
     // Bandpass Q (resonance factor).
     let bp_q = 0.5;
 
@@ -32,53 +69,34 @@ pub fn hihat_synth() -> Box<dyn AudioUnit> {
 
     // Compose the hi‑hat sound:
     // Multiply white noise by a constant amplitude, then apply the envelope and filter.
-    Box::new(noise() * constant(0.5) * env >> bandpass_hz(bp_center, bp_q))
+    Box::new(noise() * constant(gain) * env >> bandpass_hz(bp_center, bp_q))
 }
 
-/// Creates a new hi-hat pattern and adds it to the given sequencer.
+/// Creates a new hi-hat pattern with a click on every beat.
 ///
 /// # Returns
 ///
-/// A vector of `EventId`s representing the events added to the sequencer.
-pub fn new_hihat_pattern(
-    sequencer: &mut Sequencer,
-    bpm: u32,
-    drop_beats: Option<(u8, u8)>,
-) -> Vec<EventId> {
-    let mut event_ids: Vec<EventId> = Vec::new();
-    let beat_period = 60.0 / (bpm as f64);
-
-    if let Some((on, off)) = drop_beats {
-        let mut beat_start = 0.0;
-
-        // Push on beats
-        for _ in 0..on {
-            event_ids.push(sequencer.push(
-                beat_start,
-                beat_start + beat_period,
-                Fade::Smooth,
-                0.001,
-                0.001,
-                hihat_synth(),
-            ));
-            beat_start += beat_period;
-        }
+/// A vector of `Beat`s making up one cycle of the pattern.
+pub fn new_hihat_pattern() -> Vec<Beat> {
+    vec![Beat::Click(Emphasis::Normal)]
+}
 
-        // Push off beats
-        for _ in 0..off {
-            event_ids.push(sequencer.push(
-                beat_start,
-                beat_start + beat_period,
-                Fade::Smooth,
-                0.001,
-                0.001,
-                Box::new(zero()),
-            ));
-            beat_start += beat_period;
-        }
-    } else {
-        event_ids.push(sequencer.push(0.0, beat_period, Fade::Smooth, 0.001, 0.001, hihat_synth()));
-    }
+/// Constructs a hi-hat voice that plays one burst each time it is triggered.
+/// Accented clicks are louder and brighter, ghost clicks are quieter and group starts are darker.
+pub fn click_voice(emphasis: Emphasis) -> Voice {
+    let unit = match emphasis {
+        Emphasis::Normal => hihat_synth(),
+        Emphasis::Accent => click_synth(ACCENT_GAIN, ACCENT_CENTER),
+        Emphasis::Ghost => click_synth(GHOST_GAIN, CLICK_CENTER),
+        Emphasis::Group => click_synth(CLICK_GAIN, GROUP_CENTER),
+    };
+    Voice::new(unit, BURST_DURATION as f64)
+}
 
-    event_ids
+/// Constructs the quieter, brighter click played between beats when subdividing.
+pub fn subdivision_voice() -> Voice {
+    Voice::new(
+        click_synth(SUBDIVISION_GAIN, SUBDIVISION_CENTER),
+        BURST_DURATION as f64,
+    )
 }
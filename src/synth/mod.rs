@@ -1,46 +1,582 @@
+use std::collections::HashMap;
+
 use fundsp::prelude::*;
 
-use crate::config::{AppConfig, Tones};
+use crate::{
+    chord,
+    cli::Mode,
+    config::{AppConfig, Tones},
+    constants::DEFAULT_SAMPLE_RATE,
+    helpers,
+    pattern::Step,
+};
 
 pub mod hihat;
 pub mod piano;
 
+/// Extra time a voice keeps running after its envelope ends, in seconds, so filters can ring out.
+const VOICE_TAIL: f64 = 0.05;
+
+/// How far apart the two streams of a polyrhythm are panned, from 0 (center) to 1 (hard left and right).
+const POLY_SPREAD: f32 = 0.6;
+
+/// One of the two click streams of a polyrhythm.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PolyStream {
+    /// The stream that follows the beat, played on the hi-hat.
+    Base,
+    /// The stream played across the beat, played as a piano note.
+    Cross,
+}
+
+/// How strongly a beat is played.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Emphasis {
+    #[default]
+    Normal,
+    /// A louder, brighter beat, such as the downbeat of a bar.
+    Accent,
+    /// A quiet beat that is felt more than heard.
+    Ghost,
+    /// A darker beat marking the start of a group, such as each group of 2+2+3.
+    Group,
+}
+
+/// A single beat in the repeating metronome pattern.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Beat {
+    /// A hi-hat click.
+    Click(Emphasis),
+    /// Piano tones played together for the length of the beat.
+    Tones(Vec<String>, Emphasis),
+    /// A muted beat.
+    Rest,
+    /// A beat split into equal steps from a rhythm pattern, each played as its own beat.
+    Steps(Vec<Beat>),
+    /// A click from one stream of a polyrhythm.
+    Poly(PolyStream),
+    /// Beats played at the same time, such as both polyrhythm streams landing together.
+    Layers(Vec<Beat>),
+}
+
+impl Beat {
+    /// Returns the same beat played with the given emphasis.
+    /// Rests stay silent, and patterns keep the emphasis of their own steps.
+    fn with_emphasis(self, emphasis: Emphasis) -> Beat {
+        match self {
+            Beat::Click(_) => Beat::Click(emphasis),
+            Beat::Tones(notes, _) => Beat::Tones(notes, emphasis),
+            beat => beat,
+        }
+    }
+}
+
+/// A sound that plays once each time it is triggered.
+///
+/// Voices are built ahead of time and retriggered with `reset()`, so playing them never allocates.
+pub struct Voice {
+    unit: Box<dyn AudioUnit>,
+    /// How long the sound lasts once triggered, in seconds.
+    duration: f64,
+    /// Samples left to play. Silent voices are skipped.
+    remaining: u64,
+    /// Gains of the left and right channels.
+    gains: (f32, f32),
+}
+
+impl Voice {
+    pub fn new(unit: Box<dyn AudioUnit>, duration: f64) -> Self {
+        Voice {
+            unit,
+            duration: duration + VOICE_TAIL,
+            remaining: 0,
+            gains: (1.0, 1.0),
+        }
+    }
+
+    /// Pans the voice between -1 (left) and 1 (right).
+    ///
+    /// The center plays at full level on both channels, so mono output is unaffected by centered voices.
+    pub fn panned(mut self, pan: f32) -> Self {
+        self.gains = ((1.0 - pan).min(1.0), (1.0 + pan).min(1.0));
+        self
+    }
+
+    /// Restarts the sound from the beginning.
+    fn trigger(&mut self, sample_rate: f64) {
+        self.unit.reset();
+        self.remaining = (self.duration * sample_rate).ceil() as u64;
+    }
+}
+
+/// Plays the metronome pattern from prebuilt voices, along with any drone notes.
 pub struct Synth {
-    pub sequencer: Sequencer,
-    _time_events: Vec<EventId>,
-    _drone_events: Vec<EventId>,
+    /// Sounds triggered by the pattern.
+    voices: Vec<Voice>,
+    /// Indexes of the voices triggered on each step of each beat of the pattern.
+    /// Beats without their own steps have one step. Rests trigger nothing.
+    pattern: Vec<Vec<Vec<usize>>>,
+    /// Sounds that play continuously.
+    drones: Vec<Box<dyn AudioUnit>>,
+    /// Number of steps each beat is split into, cycled beat by beat. Empty without subdivisions.
+    subdivisions: Vec<u8>,
+    /// Index of the voice played on subdivision steps, if subdividing.
+    subdivision_voice: Option<usize>,
+    /// Names of the keys a transpose cycle moves through. Empty without a cycle.
+    keys: Vec<String>,
+    /// Index of the key playing. The pattern holds one sequence of beats for each key.
+    key: usize,
+    /// Number of beats in each key's sequence.
+    sequence_length: usize,
+    /// Number of beats in one pass of the progression.
+    pass_length: usize,
+    sample_rate: f64,
 }
 
 impl Synth {
     pub fn from(config: &AppConfig) -> Self {
-        let mut sequencer = Sequencer::new(true, 1);
-
         // Time events are the metronome click. They can be hihat or piano notes.
-        let _time_events = match &config.tones {
+        // A rhythm pattern replaces the beat-by-beat pattern, playing the same sound on each hit.
+        // A polyrhythm replaces it with two streams of its own.
+        let beats = match (config.poly, &config.pattern, &config.tones) {
+            (Some((cross, base)), _, _) => poly_pattern(cross, base),
+            (None, Some(bars), Some(Tones::List(tone_list))) => grid_pattern(
+                bars,
+                config.beats_per_bar(),
+                Beat::Tones(tone_list.clone(), Emphasis::Normal),
+            ),
+            (None, Some(bars), _) => {
+                grid_pattern(bars, config.beats_per_bar(), Beat::Click(Emphasis::Normal))
+            }
+            (None, None, _) => Self::beat_pattern(config),
+        };
+        // A transpose cycle plays the whole progression once in each key, so each key gets a pattern
+        // of its own and the engine picks which one plays.
+        let pass_length = beats.len();
+        let offsets = config.transpose_cycle.map_or(vec![0], chord::cycle_offsets);
+        let written_key = written_key(config);
+        let keys = match config.transpose_cycle {
+            Some(_) => offsets
+                .iter()
+                .map(|&offset| key_name(config.key, offset))
+                .collect(),
+            None => vec![],
+        };
+        let passes: Vec<Vec<Beat>> = offsets
+            .iter()
+            .map(|&offset| {
+                if offset == 0 {
+                    beats.clone()
+                } else {
+                    let flats = written_key.is_none_or(|key| key.transposed(offset).flats());
+                    piano::transposed(&beats, offset, flats)
+                }
+            })
+            .collect();
+        let passes = match &config.tones {
+            Some(Tones::Map(_)) if config.voice_lead => piano::voice_led_keys(&passes),
+            _ => passes,
+        };
+        let key_patterns: Vec<Vec<Beat>> = passes
+            .into_iter()
+            .map(|beats| arrange(config, beats))
+            .collect();
+        let sequence_length = key_patterns[0].len();
+
+        // Drone notes play continuously. They are not allowed in harmonic mode at this time.
+        let drones = if config.harmonic {
+            vec![]
+        } else {
+            piano::drone_units(config.drone.as_deref().unwrap_or(&[]))
+        };
+
+        // Build the voices for each distinct beat once, so repeated beats retrigger the same voices.
+        let mut voices: Vec<Voice> = Vec::new();
+        let mut built: HashMap<&Beat, Vec<usize>> = HashMap::new();
+        let mut voice_indexes = |beat| {
+            built
+                .entry(beat)
+                .or_insert_with(|| {
+                    let start = voices.len();
+                    voices.extend(beat_voices(beat));
+                    (start..voices.len()).collect()
+                })
+                .clone()
+        };
+        let pattern = key_patterns
+            .iter()
+            .flatten()
+            .map(|beat| match beat {
+                Beat::Steps(steps) => steps.iter().map(&mut voice_indexes).collect(),
+                beat => vec![voice_indexes(beat)],
+            })
+            .collect();
+
+        // Subdivision clicks sound the same in every mode, so they share one voice.
+        let subdivisions = subdivision_cycle(config);
+        let subdivision_voice = if subdivisions.is_empty() {
+            None
+        } else {
+            voices.push(hihat::subdivision_voice());
+            Some(voices.len() - 1)
+        };
+
+        let mut synth = Synth {
+            voices,
+            pattern,
+            drones,
+            subdivisions,
+            subdivision_voice,
+            keys,
+            key: 0,
+            sequence_length,
+            pass_length,
+            sample_rate: DEFAULT_SAMPLE_RATE as f64,
+        };
+        synth.set_sample_rate(DEFAULT_SAMPLE_RATE as f64);
+        synth
+    }
+
+    /// Builds one cycle of beats from the click or tone options.
+    fn beat_pattern(config: &AppConfig) -> Vec<Beat> {
+        match &config.tones {
             Some(tone_enum) => match tone_enum {
                 // Harmonic metronome with unchanging tones
-                Tones::List(tone_list) => piano::add_time_notes(tone_list, &mut sequencer, config),
+                Tones::List(tone_list) => piano::time_notes_pattern(tone_list),
 
                 // Harmonic metronome with a changing chord progression.
-                Tones::Map(tone_map) => {
-                    piano::add_chord_progression(tone_map, &mut sequencer, config)
-                }
+                Tones::Map(tone_map) => piano::chord_progression_pattern(tone_map, config),
             },
             // Tones were not given, so a valid CLI invocation must mean we are not in harmonic mode.
-            None => hihat::new_hihat_pattern(&mut sequencer, config.bpm, config.drop_beats),
-        };
+            None => hihat::new_hihat_pattern(),
+        }
+    }
 
-        // Drone notes play continuously. They are not allowed in harmonic mode at this time.
-        let _drone_events = if config.harmonic {
-            vec![]
+    /// Sets the sample rate of every voice and drone.
+    pub fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.sample_rate = sample_rate;
+        for voice in self.voices.iter_mut() {
+            voice.unit.set_sample_rate(sample_rate);
+        }
+        for drone in self.drones.iter_mut() {
+            drone.set_sample_rate(sample_rate);
+        }
+    }
+
+    /// The number of beats before the pattern repeats.
+    pub fn beats_per_sequence(&self) -> usize {
+        self.sequence_length
+    }
+
+    /// Names of the keys a transpose cycle moves through. Empty without a cycle.
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    /// The number of beats in one pass of the progression, after which a transpose cycle changes key.
+    pub fn pass_length(&self) -> usize {
+        self.pass_length
+    }
+
+    /// Plays the following beats in the given key of the transpose cycle.
+    pub fn set_key(&mut self, key: usize) {
+        self.key = key;
+    }
+
+    /// The index within the pattern of the given beat in the current key.
+    fn pattern_index(&self, beat: usize) -> usize {
+        self.key * self.sequence_length + beat % self.sequence_length
+    }
+
+    /// The number of steps to split a beat into. Beats from a rhythm pattern bring their own
+    /// steps, otherwise the beat follows the subdivision cycle.
+    ///
+    /// # Arguments
+    ///
+    /// * `beat` - The index of the beat within the pattern.
+    /// * `beat_count` - The number of beats played so far, which picks the place in the subdivision cycle.
+    pub fn steps_in_beat(&self, beat: usize, beat_count: u64) -> u8 {
+        let steps = self.pattern[self.pattern_index(beat)].len();
+        if steps > 1 {
+            steps as u8
+        } else if self.subdivisions.is_empty() {
+            1
         } else {
-            piano::add_drone_notes(config.drone.as_deref().unwrap_or(&[]), &mut sequencer)
-        };
+            self.subdivisions[(beat_count % self.subdivisions.len() as u64) as usize]
+        }
+    }
+
+    /// Triggers the voices for one step of the given beat.
+    ///
+    /// The first step plays the beat itself. Later steps play the beat's own pattern steps, or the
+    /// subdivision click if the beat has none. Rests stay silent.
+    ///
+    /// # Arguments
+    ///
+    /// * `beat` - The index of the beat within the pattern.
+    /// * `step` - The index of the step within the beat.
+    pub fn play_step(&mut self, beat: usize, step: u8) {
+        let steps = &self.pattern[self.pattern_index(beat)];
+        let step = step as usize;
+
+        if step == 0 || steps.len() > 1 {
+            for &index in &steps[step % steps.len()] {
+                self.voices[index].trigger(self.sample_rate);
+            }
+        } else if let Some(index) = self.subdivision_voice {
+            if !steps[0].is_empty() {
+                self.voices[index].trigger(self.sample_rate);
+            }
+        }
+    }
+
+    /// Mixes the next left and right samples from every sounding voice and drone.
+    pub fn get_stereo(&mut self) -> (f32, f32) {
+        let (mut left, mut right) = (0.0, 0.0);
+        for voice in self.voices.iter_mut().filter(|voice| voice.remaining > 0) {
+            let sample = voice.unit.get_mono();
+            left += sample * voice.gains.0;
+            right += sample * voice.gains.1;
+            voice.remaining -= 1;
+        }
+        for drone in self.drones.iter_mut() {
+            let sample = drone.get_mono();
+            left += sample;
+            right += sample;
+        }
+        (left, right)
+    }
+}
+
+/// Arranges one sequence of beats, marking group starts and accents.
+fn arrange(config: &AppConfig, beats: Vec<Beat>) -> Vec<Beat> {
+    // Accents go on last so they win over group starts.
+    let beats = match &config.groups {
+        Some(groups) => emphasis_pattern(&beats, &group_starts(groups), Emphasis::Group),
+        None => beats,
+    };
+    match &config.accents {
+        Some(accents) => emphasis_pattern(&beats, accents, Emphasis::Accent),
+        None => beats,
+    }
+}
 
-        Synth {
-            sequencer,
-            _time_events,
-            _drone_events,
+/// The key a progression is written in, for spelling it in other keys: the given key, or otherwise a
+/// major key on the root of the first chord. The root comes from the chord symbol, or from the first
+/// note of a chord given as tones, as voicings move the root away from the bottom.
+fn written_key(config: &AppConfig) -> Option<chord::Key> {
+    if config.key.is_some() {
+        return config.key;
+    }
+    let chord = config.progression.as_ref()?.first()?;
+    let root = match &config.tones {
+        Some(Tones::Map(map)) if chord::parse_symbol(chord).is_err() => map.get(chord)?.first()?,
+        _ => chord,
+    };
+    chord::Key::parse(chord::root_name(root)?, Mode::Major).ok()
+}
+
+/// Names the key of a pass moved by the given semitones: the key itself if one was given,
+/// otherwise how far the pass is from the written chords.
+fn key_name(key: Option<chord::Key>, offset: i32) -> String {
+    match (key, offset) {
+        (Some(key), _) => key.transposed(offset).name(),
+        (None, 0) => "as written".to_string(),
+        (None, _) => format!("{:+} semitones", offset),
+    }
+}
+
+/// Builds the voices that sound on the given beat.
+fn beat_voices(beat: &Beat) -> Vec<Voice> {
+    match beat {
+        Beat::Click(emphasis) => vec![hihat::click_voice(*emphasis)],
+        Beat::Tones(notes, emphasis) => {
+            let notes = match emphasis {
+                Emphasis::Normal => notes.clone(),
+                Emphasis::Accent => piano::accent_voicing(notes),
+                Emphasis::Ghost => piano::ghost_voicing(notes),
+                Emphasis::Group => piano::group_voicing(notes),
+            };
+            notes
+                .iter()
+                .map(|note| piano::note_voice(note, notes.len()))
+                .collect()
+        }
+        Beat::Poly(PolyStream::Base) => {
+            vec![hihat::click_voice(Emphasis::Normal).panned(-POLY_SPREAD)]
         }
+        Beat::Poly(PolyStream::Cross) => vec![piano::poly_voice().panned(POLY_SPREAD)],
+        Beat::Layers(beats) => beats.iter().flat_map(beat_voices).collect(),
+        // Pattern steps are built one at a time by the synth.
+        Beat::Rest | Beat::Steps(_) => vec![],
+    }
+}
+
+/// Splits the bars of a rhythm pattern into beats, with each hit playing the given beat.
+///
+/// # Arguments
+///
+/// * `bars` - The steps of each bar. Validation ensures each bar divides evenly into beats.
+/// * `beats_per_bar` - The number of beats each bar spans.
+/// * `hit` - The beat played on each hit, before emphasis.
+fn grid_pattern(bars: &[Vec<Step>], beats_per_bar: u8, hit: Beat) -> Vec<Beat> {
+    let step_beat = |step: &Step| match step {
+        Step::Hit => hit.clone(),
+        Step::Accent => hit.clone().with_emphasis(Emphasis::Accent),
+        Step::Ghost => hit.clone().with_emphasis(Emphasis::Ghost),
+        Step::Rest => Beat::Rest,
+    };
+
+    bars.iter()
+        .flat_map(|bar| bar.chunks(bar.len() / beats_per_bar as usize))
+        .map(|steps| match steps {
+            [step] => step_beat(step),
+            steps => Beat::Steps(steps.iter().map(step_beat).collect()),
+        })
+        .collect()
+}
+
+/// Interleaves two click streams over the same cycle, which lasts `base` beats.
+///
+/// The cycle is split into a grid fine enough for both streams, so each click lands exactly on a
+/// step and a changing tempo keeps the ratio exact.
+///
+/// # Arguments
+///
+/// * `cross` - The number of clicks played across the cycle.
+/// * `base` - The number of beats in the cycle, one click each.
+fn poly_pattern(cross: u8, base: u8) -> Vec<Beat> {
+    let (cross, base) = (cross as usize, base as usize);
+    let steps = helpers::lcm(cross, base);
+    let step_beat = |step: usize| {
+        let on_cross = step.is_multiple_of(steps / cross);
+        let on_base = step.is_multiple_of(steps / base);
+        match (on_cross, on_base) {
+            (true, true) => Beat::Layers(vec![
+                Beat::Poly(PolyStream::Base),
+                Beat::Poly(PolyStream::Cross),
+            ]),
+            (true, false) => Beat::Poly(PolyStream::Cross),
+            (false, true) => Beat::Poly(PolyStream::Base),
+            (false, false) => Beat::Rest,
+        }
+    };
+
+    let steps_per_beat = steps / base;
+    (0..base)
+        .map(|beat| {
+            let first = beat * steps_per_beat;
+            match steps_per_beat {
+                1 => step_beat(first),
+                _ => Beat::Steps((first..first + steps_per_beat).map(step_beat).collect()),
+            }
+        })
+        .collect()
+}
+
+/// Overlays marked beats of a bar on the pattern, repeating both until they line up.
+///
+/// # Arguments
+///
+/// * `beats` - One cycle of the pattern.
+/// * `marks` - Whether each beat of the bar gets the emphasis.
+/// * `emphasis` - The emphasis given to marked beats.
+fn emphasis_pattern(beats: &[Beat], marks: &[bool], emphasis: Emphasis) -> Vec<Beat> {
+    let length = helpers::lcm(beats.len(), marks.len());
+    (0..length)
+        .map(|i| {
+            let beat = beats[i % beats.len()].clone();
+            if marks[i % marks.len()] {
+                beat.with_emphasis(emphasis)
+            } else {
+                beat
+            }
+        })
+        .collect()
+}
+
+/// Marks the first beat of each group, i.e. 2+2+3 becomes `x.x.x..`.
+fn group_starts(groups: &[u8]) -> Vec<bool> {
+    groups
+        .iter()
+        .flat_map(|&size| (0..size).map(|beat| beat == 0))
+        .collect()
+}
+
+/// Expands the subdivision list so each entry covers `subdivide_every` beats.
+/// Empty if no subdivisions were requested.
+fn subdivision_cycle(config: &AppConfig) -> Vec<u8> {
+    // Switch once per bar by default.
+    let every = config.subdivide_every.unwrap_or(config.beats_per_bar()) as usize;
+    match &config.subdivide {
+        Some(subdivisions) => subdivisions
+            .iter()
+            .flat_map(|&steps| std::iter::repeat_n(steps, every))
+            .collect(),
+        None => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    /// Lists the steps of a polyrhythm as characters: b = base, c = cross, + = both, . = rest.
+    fn poly_steps(cross: u8, base: u8) -> String {
+        let step_char = |beat: &Beat| match beat {
+            Beat::Poly(PolyStream::Base) => 'b',
+            Beat::Poly(PolyStream::Cross) => 'c',
+            Beat::Layers(_) => '+',
+            _ => '.',
+        };
+        poly_pattern(cross, base)
+            .iter()
+            .flat_map(|beat| match beat {
+                Beat::Steps(steps) => steps.iter().map(step_char).collect::<Vec<_>>(),
+                beat => vec![step_char(beat)],
+            })
+            .collect()
+    }
+
+    #[rstest]
+    #[case(3, 2, "+.cbc.")]
+    #[case(4, 3, "+..cb.c.bc..")]
+    #[case(2, 4, "+b+b")]
+    fn poly_pattern_interleaves_streams(
+        #[case] cross: u8,
+        #[case] base: u8,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(poly_steps(cross, base), expected);
+    }
+
+    #[rstest]
+    #[case(Some("A"), 6, "Eb")]
+    #[case(Some("A"), -3, "Gb")]
+    #[case(None, 0, "as written")]
+    #[case(None, -2, "-2 semitones")]
+    fn key_name_names_each_key_of_the_cycle(
+        #[case] key: Option<&str>,
+        #[case] offset: i32,
+        #[case] expected: &str,
+    ) {
+        let key = key.map(|tonic| chord::Key::parse(tonic, Mode::Major).unwrap());
+        assert_eq!(key_name(key, offset), expected);
+    }
+
+    #[rstest]
+    fn group_starts_marks_first_beats() {
+        assert_eq!(
+            group_starts(&[2, 2, 3]),
+            vec![true, false, true, false, true, false, false]
+        );
+    }
+
+    #[rstest]
+    #[case(3, 2)]
+    #[case(7, 4)]
+    fn poly_pattern_spans_base_beats(#[case] cross: u8, #[case] base: u8) {
+        assert_eq!(poly_pattern(cross, base).len(), base as usize);
     }
 }
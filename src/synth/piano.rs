@@ -1,9 +1,19 @@
 use core::f64;
 use std::collections::HashMap;
 
-use crate::{config, helpers};
+use super::{Beat, Emphasis, Voice};
+use crate::{chord, config, helpers, voicing};
 use fundsp::prelude::*;
 
+/// Length of a note played in time, in seconds.
+const NOTE_DURATION: f32 = 0.2;
+
+/// Note played by the cross stream of a polyrhythm, high enough to stand apart from the hi-hat.
+const POLY_NOTE: &str = "E5";
+
+/// Length of a polyrhythm note in seconds, kept short so fast cross-rhythms stay distinct.
+const POLY_NOTE_DURATION: f32 = 0.08;
+
 /// Generates an electric piano-like synth sound for the given note(s).
 ///
 /// # Arguments
@@ -89,148 +99,288 @@ fn frequency_correction(freq: f32) -> f32 {
     }
 }
 
-/// Adds a series of drone notes to the sequencer.
+/// Builds a continuous tone for each drone note.
 ///
 /// # Arguments
 ///
 /// * `notes` - A slice of note strings (e.g., "C4", "E#4", "Gb4").
-/// * `sequencer` - A mutable reference to the sequencer to which the notes should be added.
 ///
 /// # Returns
 ///
-/// A vector of `EventId`s representing the events added to the sequencer.
-pub fn add_drone_notes(notes: &[String], sequencer: &mut Sequencer) -> Vec<EventId> {
-    let mut events: Vec<EventId> = Vec::new();
-
-    for note in notes {
-        events.push(sequencer.push(
-            0.0,
-            f64::INFINITY,
-            Fade::Smooth,
-            0.001,
-            0.001,
-            electric_piano(note, None, notes.len()),
-        ));
-    }
+/// A vector of units that each sustain one note indefinitely.
+pub fn drone_units(notes: &[String]) -> Vec<Box<dyn AudioUnit>> {
+    notes
+        .iter()
+        .map(|note| electric_piano(note, None, notes.len()))
+        .collect()
+}
+
+/// Constructs a voice that plays a short piano note each time it is triggered.
+///
+/// # Arguments
+///
+/// * `note` - A note string (e.g., "C4", "E#4", "Gb4").
+/// * `num_total_notes` - The total number of notes in the chord played together.
+pub fn note_voice(note: &str, num_total_notes: usize) -> Voice {
+    Voice::new(
+        electric_piano(note, Some(NOTE_DURATION), num_total_notes),
+        NOTE_DURATION as f64,
+    )
+}
 
-    events
+/// Constructs the pitched voice played by the cross stream of a polyrhythm.
+pub fn poly_voice() -> Voice {
+    Voice::new(
+        electric_piano(POLY_NOTE, Some(POLY_NOTE_DURATION), 1),
+        POLY_NOTE_DURATION as f64,
+    )
 }
 
-/// Adds a series of notes to the sequencer at regular intervals.
-/// The notes will play for the specified duration and be spaced by the beat duration.
+/// Voices notes for an accented beat by doubling the first note an octave higher.
+/// Notes are left as they are if the octave above is out of range.
 ///
 /// # Arguments
 ///
 /// * `notes` - A slice of note strings (e.g., "C4", "E#4", "Gb4").
-/// * `sequencer` - A mutable reference to the sequencer to which the notes should be added.
-/// * `bpm` - The beats per minute for the sequencer.
-/// * `drop_beats` - An optional tuple of two u8 values representing the number of on and off beats to drop.
+pub fn accent_voicing(notes: &[String]) -> Vec<String> {
+    let mut voiced = notes.to_vec();
+    if let Some(doubled) = notes.first().and_then(|note| shift_octave(note, 1)) {
+        voiced.push(doubled);
+    }
+    voiced
+}
+
+/// Voices notes for the start of a group by doubling the first note an octave lower.
+/// Notes are left as they are if the octave below is out of range.
 ///
-/// # Returns
+/// # Arguments
 ///
-/// A vector of `EventId`s representing the events added to the sequencer.
-pub fn add_time_notes(
-    notes: &[String],
-    sequencer: &mut Sequencer,
-    config: &config::AppConfig,
-) -> Vec<EventId> {
-    let mut events: Vec<EventId> = Vec::new();
-    let beat_period = 60.0 / (config.bpm as f64);
-    let note_duration: f32 = 0.2; // seconds
-
-    if let Some((on, off)) = config.drop_beats {
-        let mut beat_start = 0.0;
-
-        // Push on beats
-        for _ in 0..on {
-            for note in notes {
-                events.push(sequencer.push(
-                    beat_start,
-                    beat_start + beat_period,
-                    Fade::Smooth,
-                    0.001,
-                    0.001,
-                    electric_piano(note, Some(note_duration), notes.len()),
-                ));
-            }
-            beat_start += beat_period;
-        }
-
-        // Push off beats
-        for _ in 0..off {
-            events.push(sequencer.push(
-                beat_start,
-                beat_start + beat_period,
-                Fade::Smooth,
-                0.001,
-                0.001,
-                Box::new(zero()),
-            ));
-            beat_start += beat_period;
-        }
-    } else {
-        for note in notes {
-            events.push(sequencer.push(
-                0.0,
-                beat_period,
-                Fade::Smooth,
-                0.001,
-                0.001,
-                electric_piano(note, Some(note_duration), notes.len()),
-            ));
-        }
+/// * `notes` - A slice of note strings (e.g., "C4", "E#4", "Gb4").
+pub fn group_voicing(notes: &[String]) -> Vec<String> {
+    let mut voiced = notes.to_vec();
+    if let Some(doubled) = notes.first().and_then(|note| shift_octave(note, -1)) {
+        voiced.push(doubled);
     }
+    voiced
+}
+
+/// Voices notes for a ghost beat with only the first note, for a lighter sound.
+///
+/// # Arguments
+///
+/// * `notes` - A slice of note strings (e.g., "C4", "E#4", "Gb4").
+pub fn ghost_voicing(notes: &[String]) -> Vec<String> {
+    notes.iter().take(1).cloned().collect()
+}
 
-    events
+/// Moves a note string up or down by the given number of octaves, if the result is a known note.
+fn shift_octave(note: &str, octaves: i8) -> Option<String> {
+    let split = note.find(|c: char| c.is_ascii_digit())?;
+    let (name, octave) = note.split_at(split);
+    let shifted = format!("{}{}", name, octave.parse::<i8>().ok()? + octaves);
+    helpers::note_to_frequency(&shifted).map(|_| shifted)
+}
+
+/// Creates a pattern that plays the same notes on every beat.
+///
+/// # Arguments
+///
+/// * `notes` - A slice of note strings (e.g., "C4", "E#4", "Gb4").
+///
+/// # Returns
+///
+/// A vector of `Beat`s making up one cycle of the pattern.
+pub fn time_notes_pattern(notes: &[String]) -> Vec<Beat> {
+    vec![Beat::Tones(notes.to_vec(), Emphasis::Normal)]
 }
 
-/// Adds a chord progression to the sequencer. Each chord in the progression will play for one beat.
+/// Creates a chord progression pattern. Each chord in the progression plays for its number of beats.
 ///
 /// # Arguments
 /// * `tone_map` - A map of chord names to note strings (e.g., "C4", "E#4", "Gb4").
-/// * `sequencer` - A mutable reference to the sequencer to which the notes should be added.
 /// * `config` - The application configuration.
 ///
 /// # Returns
 ///
-/// A vector of `EventId`s representing the events added to the sequencer.
-pub fn add_chord_progression(
+/// A vector of `Beat`s making up one pass through the progression.
+pub fn chord_progression_pattern(
     tone_map: &HashMap<String, Vec<String>>,
-    sequencer: &mut Sequencer,
     config: &config::AppConfig,
-) -> Vec<EventId> {
-    let mut events: Vec<EventId> = Vec::new();
-    let beat_period = 60.0 / (config.bpm as f64);
-    let note_duration: f32 = 0.2; // seconds
-
-    let mut beat_start = 0.0;
+) -> Vec<Beat> {
+    let mut pattern: Vec<Beat> = Vec::new();
 
     // If this function is run, validation has already ensured that both progression and beats_per are Some.
     if let (Some(progression), Some(beats_per)) =
         (config.progression.as_ref(), config.beats_per.as_ref())
     {
-        // Zip the progression and beats_per vectors together to get the chord and beats for each iteration.
-        for (chord, &beats) in progression.iter().zip(beats_per.iter()) {
-            println!("Chord: {}, Beats: {}", chord, beats);
+        // A single voicing applies to every chord, like a single beats-per value.
+        let voicings = config.voicing.as_deref().unwrap_or(&[]);
+
+        // A single beats-per value applies to every chord, so cycle it alongside the progression.
+        for (index, (chord, &beats)) in progression.iter().zip(beats_per.iter().cycle()).enumerate()
+        {
             if let Some(notes) = tone_map.get(chord) {
-                println!("Notes: {:?}", notes);
-                // Play each note in the chord for the specified number of beats.
-                for _ in 0..beats {
-                    for note in notes {
-                        events.push(sequencer.push(
-                            beat_start,
-                            beat_start + beat_period,
-                            Fade::Smooth,
-                            0.001,
-                            0.001,
-                            electric_piano(note, Some(note_duration), notes.len()),
-                        ));
-                    }
-                    beat_start += beat_period;
-                }
+                let notes = match voicings.get(index).or(voicings.first()) {
+                    Some(&style) => voicing::voice(notes, style),
+                    None => notes.clone(),
+                };
+                pattern.extend(vec![Beat::Tones(notes, Emphasis::Normal); beats as usize]);
             }
         }
     }
 
-    events
+    pattern
+}
+
+/// Voices each chord of a progression from the chord before it, so the voices move as little as possible.
+///
+/// The progression is led through twice, and the second time is kept, so the first chord is also led
+/// from the last one when the progression repeats. Beats that repeat a chord keep its voicing.
+pub fn voice_led(beats: &[Beat]) -> Vec<Beat> {
+    let first = voice_led_from(beats, None);
+    let last = first.iter().rev().find_map(|beat| match beat {
+        Beat::Tones(notes, _) => Some(notes.clone()),
+        _ => None,
+    });
+    voice_led_from(beats, last)
+}
+
+/// Voices a progression played once in each key of a transpose cycle, so each key leads on from the
+/// last chord of the key before it, and the last key leads back into the first.
+pub fn voice_led_keys(passes: &[Vec<Beat>]) -> Vec<Vec<Beat>> {
+    let Some(pass_length) = passes.first().map(Vec::len).filter(|&length| length > 0) else {
+        return passes.to_vec();
+    };
+    voice_led(&passes.concat())
+        .chunks(pass_length)
+        .map(<[Beat]>::to_vec)
+        .collect()
+}
+
+/// Voices each chord of a progression from the one before it, starting from the given voicing if any.
+fn voice_led_from(beats: &[Beat], mut voiced: Option<Vec<String>>) -> Vec<Beat> {
+    let mut written: Option<&Vec<String>> = None;
+    beats
+        .iter()
+        .map(|beat| match beat {
+            Beat::Tones(notes, emphasis) => {
+                let notes_voiced = match (&voiced, written) {
+                    (Some(previous), Some(written)) if written == notes => previous.clone(),
+                    (Some(previous), _) => chord::voice_lead(previous, notes),
+                    (None, _) => notes.clone(),
+                };
+                written = Some(notes);
+                voiced = Some(notes_voiced.clone());
+                Beat::Tones(notes_voiced, *emphasis)
+            }
+            beat => beat.clone(),
+        })
+        .collect()
+}
+
+/// Moves every chord of a progression by the given number of semitones.
+///
+/// # Arguments
+///
+/// * `beats` - The beats of the progression.
+/// * `semitones` - How far to move each chord, up or down.
+/// * `flats` - Whether to spell the moved chords with flats, following the key they are moved to.
+pub fn transposed(beats: &[Beat], semitones: i32, flats: bool) -> Vec<Beat> {
+    beats
+        .iter()
+        .map(|beat| match beat {
+            Beat::Tones(notes, emphasis) => {
+                Beat::Tones(chord::transpose(notes, semitones, flats), *emphasis)
+            }
+            beat => beat.clone(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn notes(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[rstest]
+    #[case(&["C4", "E4", "G4"], &["C4", "E4", "G4", "C5"])]
+    #[case(&["Bb3", "D4"], &["Bb3", "D4", "Bb4"])]
+    #[case(&["B#5"], &["B#5"])]
+    fn accent_voicing_doubles_the_first_note(#[case] input: &[&str], #[case] expected: &[&str]) {
+        assert_eq!(accent_voicing(&notes(input)), notes(expected));
+    }
+
+    #[rstest]
+    #[case(&["C4", "E4", "G4"], &["C4", "E4", "G4", "C3"])]
+    #[case(&["Cb2"], &["Cb2"])]
+    fn group_voicing_doubles_the_first_note_lower(
+        #[case] input: &[&str],
+        #[case] expected: &[&str],
+    ) {
+        assert_eq!(group_voicing(&notes(input)), notes(expected));
+    }
+
+    #[rstest]
+    fn ghost_voicing_keeps_the_first_note() {
+        assert_eq!(ghost_voicing(&notes(&["C4", "E4", "G4"])), notes(&["C4"]));
+    }
+
+    #[rstest]
+    fn transposed_moves_every_chord() {
+        let pass = vec![
+            Beat::Tones(notes(&["C3", "E3", "G3"]), Emphasis::Normal),
+            Beat::Rest,
+            Beat::Tones(notes(&["G3", "B3", "D4"]), Emphasis::Accent),
+        ];
+        assert_eq!(
+            transposed(&pass, 5, true),
+            vec![
+                Beat::Tones(notes(&["F3", "A3", "C4"]), Emphasis::Normal),
+                Beat::Rest,
+                Beat::Tones(notes(&["C4", "E4", "G4"]), Emphasis::Accent),
+            ]
+        );
+    }
+
+    #[rstest]
+    fn voice_led_keeps_repeated_chords_and_leads_the_repeat() {
+        let pass = vec![
+            Beat::Tones(notes(&["C3", "E3", "G3"]), Emphasis::Normal),
+            Beat::Tones(notes(&["C3", "E3", "G3"]), Emphasis::Normal),
+            Beat::Tones(notes(&["F3", "A3", "C4"]), Emphasis::Normal),
+            Beat::Tones(notes(&["F3", "A3", "C4"]), Emphasis::Normal),
+        ];
+        let led = voice_led(&pass);
+        assert_eq!(
+            led,
+            vec![
+                Beat::Tones(notes(&["C3", "E3", "G3"]), Emphasis::Normal),
+                Beat::Tones(notes(&["C3", "E3", "G3"]), Emphasis::Normal),
+                Beat::Tones(notes(&["C3", "F3", "A3"]), Emphasis::Normal),
+                Beat::Tones(notes(&["C3", "F3", "A3"]), Emphasis::Normal),
+            ]
+        );
+    }
+
+    #[rstest]
+    fn voice_led_keys_leads_each_key_from_the_one_before() {
+        let pass = vec![
+            Beat::Tones(notes(&["C3", "E3", "G3"]), Emphasis::Normal),
+            Beat::Tones(notes(&["G3", "B3", "D4"]), Emphasis::Normal),
+        ];
+        let passes = vec![pass.clone(), transposed(&pass, 5, true)];
+        let led = voice_led_keys(&passes);
+        // F major follows the G major that ends the first key, where on its own it would start on F3.
+        assert_eq!(
+            led[1],
+            vec![
+                Beat::Tones(notes(&["C3", "F3", "A3"]), Emphasis::Normal),
+                Beat::Tones(notes(&["C3", "E3", "G3"]), Emphasis::Normal),
+            ]
+        );
+    }
 }
@@ -0,0 +1,144 @@
+use crate::{cli::StepEnd, config::AppConfig};
+
+/// Steps the tempo from the starting BPM toward a target by a fixed amount every few beats,
+/// like a speed trainer.
+#[derive(Clone, Debug)]
+pub struct StepTrainer {
+    /// Starting tempo.
+    start: f64,
+    /// Tempo where stepping stops.
+    until: f64,
+    /// Size of each step in BPM.
+    step: f64,
+    /// Beats to play at each tempo before stepping.
+    every: u32,
+    /// What to do once the target is reached.
+    at_end: StepEnd,
+    /// The current tempo.
+    current: f64,
+    /// Beats played at the current tempo.
+    beats: u32,
+    /// Whether the tempo is heading toward the target rather than back to the start.
+    outward: bool,
+}
+
+impl StepTrainer {
+    pub fn new(start: f64, until: f64, step: f64, every: u32, at_end: StepEnd) -> Self {
+        StepTrainer {
+            start,
+            until,
+            step: step.abs(),
+            every,
+            at_end,
+            current: start,
+            beats: 0,
+            outward: true,
+        }
+    }
+
+    /// Builds the trainer from the app config, if a step was given.
+    pub fn from_config(config: &AppConfig) -> Option<Self> {
+        match (config.step, config.every, config.until) {
+            (Some(step), Some(every), Some(until)) => Some(StepTrainer::new(
+                config.bpm,
+                until,
+                step,
+                every,
+                config.at_end,
+            )),
+            _ => None,
+        }
+    }
+
+    /// Counts the start of a beat, stepping the tempo once enough beats have played at the current one.
+    ///
+    /// # Returns
+    ///
+    /// The new tempo if it changed on this beat.
+    pub fn next_beat(&mut self) -> Option<f64> {
+        if self.beats < self.every {
+            self.beats += 1;
+            return None;
+        }
+        self.beats = 1;
+
+        let target = if self.outward { self.until } else { self.start };
+        if self.current == target {
+            match self.at_end {
+                StepEnd::Hold => return None,
+                StepEnd::Reset => {
+                    self.current = self.start;
+                    return Some(self.current);
+                }
+                // Turn around at either end.
+                StepEnd::Reverse => self.outward = !self.outward,
+            }
+        }
+
+        let target = if self.outward { self.until } else { self.start };
+        self.current = if target > self.current {
+            (self.current + self.step).min(target)
+        } else {
+            (self.current - self.step).max(target)
+        };
+        Some(self.current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    /// Lists the tempo of each of the given number of beats.
+    fn tempos(trainer: &mut StepTrainer, beats: usize) -> Vec<f64> {
+        let mut bpm = trainer.start;
+        (0..beats)
+            .map(|_| {
+                if let Some(next) = trainer.next_beat() {
+                    bpm = next;
+                }
+                bpm
+            })
+            .collect()
+    }
+
+    #[rstest]
+    fn steps_after_every_beats() {
+        let mut trainer = StepTrainer::new(100.0, 110.0, 5.0, 2, StepEnd::Hold);
+        assert_eq!(
+            tempos(&mut trainer, 8),
+            vec![100.0, 100.0, 105.0, 105.0, 110.0, 110.0, 110.0, 110.0]
+        );
+    }
+
+    #[rstest]
+    fn last_step_stops_at_until() {
+        let mut trainer = StepTrainer::new(100.0, 108.0, 5.0, 1, StepEnd::Hold);
+        assert_eq!(tempos(&mut trainer, 4), vec![100.0, 105.0, 108.0, 108.0]);
+    }
+
+    #[rstest]
+    fn steps_down_toward_a_lower_until() {
+        let mut trainer = StepTrainer::new(120.0, 110.0, 5.0, 1, StepEnd::Hold);
+        assert_eq!(tempos(&mut trainer, 4), vec![120.0, 115.0, 110.0, 110.0]);
+    }
+
+    #[rstest]
+    fn reset_returns_to_the_start() {
+        let mut trainer = StepTrainer::new(100.0, 110.0, 5.0, 1, StepEnd::Reset);
+        assert_eq!(
+            tempos(&mut trainer, 6),
+            vec![100.0, 105.0, 110.0, 100.0, 105.0, 110.0]
+        );
+    }
+
+    #[rstest]
+    fn reverse_steps_back_and_forth() {
+        let mut trainer = StepTrainer::new(100.0, 110.0, 5.0, 1, StepEnd::Reverse);
+        assert_eq!(
+            tempos(&mut trainer, 7),
+            vec![100.0, 105.0, 110.0, 105.0, 100.0, 105.0, 110.0]
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use crate::{chord, cli::Voicing};
+
+/// Arranges the notes of a chord in a voicing style, keeping it within the supported range.
+///
+/// The first note is taken as the root, and the other notes are read as pitch classes above it, so a
+/// chord can be written in any octave. Notes that can't be read are left out.
+///
+/// # Arguments
+///
+/// * `notes` - Note names such as `C3` or `Eb4`, starting with the root.
+/// * `voicing` - How to arrange the notes.
+pub fn voice(notes: &[String], voicing: Voicing) -> Vec<String> {
+    let numbers: Vec<i32> = notes
+        .iter()
+        .filter_map(|note| chord::note_number(note))
+        .collect();
+    let close = close_position(&numbers);
+    let voiced = match voicing {
+        Voicing::Root => close,
+        Voicing::First => invert(close, 1),
+        Voicing::Second => invert(close, 2),
+        Voicing::Drop2 => drop(close, 2),
+        Voicing::Drop3 => drop(close, 3),
+        Voicing::Shell => shell(&close),
+        Voicing::Spread => spread(&close),
+    };
+
+    let flats = chord::spelled_with_flats(notes);
+    chord::fit_range(&voiced)
+        .iter()
+        .map(|&number| chord::note_name(number, flats))
+        .collect()
+}
+
+/// Stacks the notes closely above the first, within an octave. A note doubling the root is kept an
+/// octave above it.
+fn close_position(numbers: &[i32]) -> Vec<i32> {
+    let Some((&root, rest)) = numbers.split_first() else {
+        return vec![];
+    };
+    let mut intervals: Vec<i32> = rest
+        .iter()
+        .map(|number| (number - root - 1).rem_euclid(12) + 1)
+        .collect();
+    intervals.sort_unstable();
+    std::iter::once(root)
+        .chain(intervals.iter().map(|interval| root + interval))
+        .collect()
+}
+
+/// Moves the lowest notes up an octave. At least one note is left in place.
+fn invert(mut close: Vec<i32>, notes: usize) -> Vec<i32> {
+    let moved = notes.min(close.len().saturating_sub(1));
+    close[..moved].iter_mut().for_each(|number| *number += 12);
+    close.sort_unstable();
+    close
+}
+
+/// Drops the given note, counting down from the top, an octave. Chords with too few notes are left as they are.
+fn drop(mut close: Vec<i32>, from_top: usize) -> Vec<i32> {
+    if close.len() > from_top {
+        let index = close.len() - from_top;
+        close[index] -= 12;
+        close.sort_unstable();
+    }
+    close
+}
+
+/// Keeps the root, the third and the seventh or sixth.
+///
+/// Suspended chords keep their second or fourth in place of the third, and chords without a seventh
+/// or sixth keep their top note.
+fn shell(close: &[i32]) -> Vec<i32> {
+    let Some(&root) = close.first() else {
+        return vec![];
+    };
+    let find = |intervals: &[i32]| {
+        close
+            .iter()
+            .rev()
+            .find(|&&number| intervals.contains(&(number - root)))
+            .copied()
+    };
+    let third = find(&[3, 4]).or_else(|| find(&[2, 5]));
+    let seventh = find(&[9, 10, 11]).or(close.last().copied());
+
+    let mut shell = vec![root];
+    for number in [third, seventh].into_iter().flatten() {
+        if !shell.contains(&number) {
+            shell.push(number);
+        }
+    }
+    shell.sort_unstable();
+    shell
+}
+
+/// Keeps the root in the bass and moves every other note up an octave.
+fn spread(close: &[i32]) -> Vec<i32> {
+    close
+        .iter()
+        .enumerate()
+        .map(|(index, number)| if index == 0 { *number } else { number + 12 })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn notes(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[rstest]
+    #[case(Voicing::Root, &["C3", "E3", "G3", "B3"])]
+    #[case(Voicing::First, &["E3", "G3", "B3", "C4"])]
+    #[case(Voicing::Second, &["G3", "B3", "C4", "E4"])]
+    #[case(Voicing::Drop2, &["G2", "C3", "E3", "B3"])]
+    #[case(Voicing::Drop3, &["E2", "C3", "G3", "B3"])]
+    #[case(Voicing::Shell, &["C3", "E3", "B3"])]
+    #[case(Voicing::Spread, &["C3", "E4", "G4", "B4"])]
+    fn voice_arranges_a_seventh_chord(#[case] voicing: Voicing, #[case] expected: &[&str]) {
+        assert_eq!(
+            voice(&notes(&["C3", "E3", "G3", "B3"]), voicing),
+            notes(expected)
+        );
+    }
+
+    #[rstest]
+    // Notes written out of order or in other octaves are read above the root.
+    #[case(&["C3", "G4", "E2"], Voicing::Root, &["C3", "E3", "G3"])]
+    #[case(&["D3", "F3", "A3", "C4"], Voicing::Shell, &["D3", "F3", "C4"])]
+    #[case(&["C3", "E3", "G3", "A3"], Voicing::Shell, &["C3", "E3", "A3"])]
+    #[case(&["C3", "F3", "G3"], Voicing::Shell, &["C3", "F3", "G3"])]
+    #[case(&["C3", "E3", "G3"], Voicing::Drop3, &["C3", "E3", "G3"])]
+    #[case(&["Bb3", "D4", "F4"], Voicing::First, &["D4", "F4", "Bb4"])]
+    fn voice_reads_the_first_note_as_the_root(
+        #[case] input: &[&str],
+        #[case] voicing: Voicing,
+        #[case] expected: &[&str],
+    ) {
+        assert_eq!(voice(&notes(input), voicing), notes(expected));
+    }
+
+    #[rstest]
+    fn voice_stays_in_range() {
+        assert_eq!(
+            voice(&notes(&["A5", "C6", "E6"]), Voicing::Spread),
+            notes(&["A3", "C5", "E5"])
+        );
+        assert_eq!(
+            voice(&notes(&["C2", "E2", "G2", "B2"]), Voicing::Drop3),
+            notes(&["E2", "C3", "G3", "B3"])
+        );
+    }
+}